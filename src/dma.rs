@@ -14,11 +14,11 @@ pub use embedded_dma::{ReadBuffer, WriteBuffer};
 use crate::{
     pac::{self, dma1::ch::cr},
     rcc::AHB,
-    serial,
+    serial, spi,
 };
 use core::{
     convert::TryFrom,
-    mem,
+    mem, slice,
     sync::atomic::{self, Ordering},
 };
 
@@ -132,6 +132,12 @@ impl<B, C: Channel, T: Target> Transfer<B, C, T> {
         inner.channel.event_occurred(Event::TransferComplete)
     }
 
+    /// Number of words this transfer still has to move
+    pub fn remaining(&self) -> u16 {
+        let inner = crate::unwrap!(self.inner.as_ref());
+        inner.channel.remaining_transfers()
+    }
+
     /// Stop this transfer and return ownership over its parts
     pub fn stop(mut self) -> (B, C, T) {
         let mut inner = crate::unwrap!(self.inner.take());
@@ -156,7 +162,170 @@ impl<B, C: Channel, T: Target> Drop for Transfer<B, C, T> {
     }
 }
 
-/// This only exists so we can implement `Drop` for `Transfer`.
+/// An in-progress circular DMA transfer between a peripheral and a buffer
+///
+/// The channel keeps filling the buffer, wrapping around at its end, until the transfer is
+/// stopped. The received data is consumed with [`CircTransfer::read_available`], which has to
+/// be called often enough that the channel never overtakes the reader. Data which is
+/// overwritten before it was read is lost without notice.
+///
+/// In the other direction, started with [`CircTransfer::start_read`], the channel keeps
+/// sending the buffer, restarting at its beginning.
+pub struct CircTransfer<B, C: Channel, T: Target> {
+    // This is always a `Some` outside of `drop`.
+    inner: Option<TransferInner<B, C, T>>,
+    read_index: usize,
+}
+
+impl<B, C: Channel, T: Target> CircTransfer<B, C, T> {
+    /// Start a circular DMA write transfer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is empty or longer than 65535 words.
+    pub fn start_write(mut buffer: B, mut channel: C, mut target: T) -> Self
+    where
+        B: WriteBuffer + 'static,
+        T: OnChannel<C>,
+    {
+        // NOTE(unsafe) See `Transfer::start_write`.
+        let (ptr, len) = unsafe { buffer.write_buffer() };
+        crate::assert!(len > 0);
+        let len = crate::expect!(u16::try_from(len).ok(), "buffer is too large");
+
+        // NOTE(unsafe) We are using the address of a 'static WriteBuffer here,
+        // which is guaranteed to be safe for DMA.
+        unsafe { channel.set_memory_address(ptr as u32, Increment::Enable) };
+        channel.set_transfer_length(len);
+        channel.set_word_size::<B::Word>();
+        channel.set_direction(Direction::FromPeripheral);
+        channel.set_circular(true);
+
+        crate::assert!(!channel.is_enabled());
+
+        atomic::compiler_fence(Ordering::Release);
+
+        target.enable_dma();
+        channel.enable();
+
+        Self {
+            inner: Some(TransferInner {
+                buffer,
+                channel,
+                target,
+            }),
+            read_index: 0,
+        }
+    }
+
+    /// Start a circular DMA read transfer.
+    ///
+    /// Updating the buffer while it is sent requires its owner to provide interior
+    /// mutability. The part of the buffer behind the channel, see
+    /// [`CircTransfer::remaining`], can be rewritten safely.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is empty or longer than 65535 words.
+    pub fn start_read(buffer: B, mut channel: C, mut target: T) -> Self
+    where
+        B: ReadBuffer + 'static,
+        T: OnChannel<C>,
+    {
+        // NOTE(unsafe) See `Transfer::start_read`.
+        let (ptr, len) = unsafe { buffer.read_buffer() };
+        crate::assert!(len > 0);
+        let len = crate::expect!(u16::try_from(len).ok(), "buffer is too large");
+
+        // NOTE(unsafe) We are using the address of a 'static ReadBuffer here,
+        // which is guaranteed to be safe for DMA.
+        unsafe { channel.set_memory_address(ptr as u32, Increment::Enable) };
+        channel.set_transfer_length(len);
+        channel.set_word_size::<B::Word>();
+        channel.set_direction(Direction::FromMemory);
+        channel.set_circular(true);
+
+        crate::assert!(!channel.is_enabled());
+
+        atomic::compiler_fence(Ordering::Release);
+
+        target.enable_dma();
+        channel.enable();
+
+        Self {
+            inner: Some(TransferInner {
+                buffer,
+                channel,
+                target,
+            }),
+            read_index: 0,
+        }
+    }
+
+    /// Number of words the channel moves before it wraps around
+    pub fn remaining(&self) -> u16 {
+        let inner = crate::unwrap!(self.inner.as_ref());
+        inner.channel.remaining_transfers()
+    }
+
+    /// Return the words written by the channel since the last call
+    ///
+    /// The data is returned as two slices, as it may wrap around the end of the buffer.
+    /// The second slice is only non-empty if that is the case.
+    pub fn read_available(&mut self) -> (&[B::Word], &[B::Word])
+    where
+        B: WriteBuffer,
+    {
+        let inner = crate::unwrap!(self.inner.as_mut());
+
+        // NOTE(unsafe) `WriteBuffer` guarantees that repeated calls return the same
+        // buffer, which we only read from.
+        let (ptr, len) = unsafe { inner.buffer.write_buffer() };
+        // NDTR is reloaded immediately after reaching zero, but be defensive about it
+        let write_index = (len - usize::from(inner.channel.remaining_transfers())) % len;
+
+        atomic::compiler_fence(Ordering::Acquire);
+
+        let read_index = self.read_index;
+        self.read_index = write_index;
+
+        // NOTE(unsafe) both ranges lie within the buffer and the channel is not going to
+        // write to them, as long as it does not overtake the reader.
+        unsafe {
+            if write_index >= read_index {
+                (
+                    slice::from_raw_parts(ptr.add(read_index), write_index - read_index),
+                    slice::from_raw_parts(ptr, 0),
+                )
+            } else {
+                (
+                    slice::from_raw_parts(ptr.add(read_index), len - read_index),
+                    slice::from_raw_parts(ptr, write_index),
+                )
+            }
+        }
+    }
+
+    /// Stop this transfer and return ownership over its parts
+    pub fn stop(mut self) -> (B, C, T) {
+        let mut inner = crate::unwrap!(self.inner.take());
+        inner.stop();
+        inner.channel.set_circular(false);
+
+        (inner.buffer, inner.channel, inner.target)
+    }
+}
+
+impl<B, C: Channel, T: Target> Drop for CircTransfer<B, C, T> {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.as_mut() {
+            inner.stop();
+            inner.channel.set_circular(false);
+        }
+    }
+}
+
+/// This only exists so we can implement `Drop` for `Transfer` and `CircTransfer`.
 struct TransferInner<B, C, T> {
     buffer: B,
     channel: C,
@@ -319,6 +488,26 @@ pub trait Channel: private::Channel {
         self.ch().ndtr.write(|w| w.ndt().bits(len));
     }
 
+    /// Number of words the channel still has to transfer before the
+    /// transfer is complete (or, in circular mode, before it wraps around).
+    fn remaining_transfers(&self) -> u16 {
+        self.ch().ndtr.read().ndt().bits()
+    }
+
+    /// Enable or disable circular mode, in which the channel restarts
+    /// at the beginning of the memory area after each completed transfer.
+    ///
+    /// Only call this method on disabled channels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this channel is enabled.
+    fn set_circular(&mut self, circular: bool) {
+        crate::assert!(!self.is_enabled());
+
+        self.ch().cr.modify(|_, w| w.circ().bit(circular));
+    }
+
     /// Set the word size.
     ///
     /// # Panics
@@ -557,6 +746,13 @@ macro_rules! on_channel {
     ) => {
         $( unsafe impl OnChannel<$dma::$C> for $target {} )+
     };
+
+    (
+        $dma:ident, <$T:ident>,
+        $( $target:ty => $C:ident, )+
+    ) => {
+        $( unsafe impl<$T> OnChannel<$dma::$C> for $target {} )+
+    };
 }
 
 on_channel!(dma1,
@@ -567,3 +763,76 @@ on_channel!(dma1,
     serial::Rx<pac::USART3> => C3,
     serial::Tx<pac::USART3> => C2,
 );
+
+#[cfg(any(
+    feature = "stm32f302xb",
+    feature = "stm32f302xc",
+    feature = "stm32f302xd",
+    feature = "stm32f302xe",
+    feature = "stm32f303",
+))]
+on_channel!(dma1, <WORD>,
+    spi::Rx<pac::SPI1, WORD> => C2,
+    spi::Tx<pac::SPI1, WORD> => C3,
+);
+
+#[cfg(any(
+    feature = "stm32f302",
+    feature = "stm32f303xb",
+    feature = "stm32f303xc",
+    feature = "stm32f303xd",
+    feature = "stm32f303xe",
+))]
+on_channel!(dma1, <WORD>,
+    spi::Rx<pac::SPI2, WORD> => C4,
+    spi::Tx<pac::SPI2, WORD> => C5,
+);
+
+#[cfg(any(
+    feature = "stm32f302xb",
+    feature = "stm32f302xc",
+    feature = "stm32f302xd",
+    feature = "stm32f302xe",
+    feature = "stm32f303xb",
+    feature = "stm32f303xc",
+    feature = "stm32f303xd",
+    feature = "stm32f303xe",
+))]
+on_channel!(dma2, <WORD>,
+    spi::Rx<pac::SPI3, WORD> => C1,
+    spi::Tx<pac::SPI3, WORD> => C2,
+);
+
+// The extensions share the request lines of the SPI direction they don't use
+#[cfg(any(
+    feature = "stm32f303xb",
+    feature = "stm32f303xc",
+    feature = "stm32f303xd",
+    feature = "stm32f303xe",
+))]
+on_channel!(dma1,
+    crate::i2s::ExtRx<pac::I2S2EXT> => C4,
+    crate::i2s::ExtTx<pac::I2S2EXT> => C5,
+);
+
+#[cfg(any(
+    feature = "stm32f303xb",
+    feature = "stm32f303xc",
+    feature = "stm32f303xd",
+    feature = "stm32f303xe",
+))]
+on_channel!(dma2,
+    crate::i2s::ExtRx<pac::I2S3EXT> => C1,
+    crate::i2s::ExtTx<pac::I2S3EXT> => C2,
+);
+
+#[cfg(any(
+    feature = "stm32f302xd",
+    feature = "stm32f302xe",
+    feature = "stm32f303xd",
+    feature = "stm32f303xe",
+))]
+on_channel!(dma2, <WORD>,
+    spi::Rx<pac::SPI4, WORD> => C4,
+    spi::Tx<pac::SPI4, WORD> => C5,
+);
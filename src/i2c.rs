@@ -4,12 +4,16 @@
 //!
 //! [examples/i2c_scanner.rs]: https://github.com/stm32-rs/stm32f3xx-hal/blob/v0.6.1/examples/i2c_scanner.rs
 
-use core::{convert::TryFrom, ops::Deref};
+use core::ops::Deref;
 
 use crate::{
     gpio::{gpioa, gpiob, OpenDrain, AF4},
-    hal::blocking::i2c::{Read, Write, WriteRead},
-    pac::{i2c1::RegisterBlock, rcc::cfgr3::I2C1SW_A, I2C1, RCC},
+    hal::blocking::i2c::{Read, SevenBitAddress, TenBitAddress, Write, WriteRead},
+    pac::{
+        i2c1::{cr2, RegisterBlock},
+        rcc::cfgr3::I2C1SW_A,
+        I2C1, RCC,
+    },
     rcc::{Clocks, APB1},
     time::rate::*,
 };
@@ -25,6 +29,12 @@ use crate::{
 
 use cfg_if::cfg_if;
 
+pub mod nonblocking;
+pub mod slave;
+pub mod timing;
+
+use timing::Timing;
+
 /// I2C error
 #[derive(Debug)]
 #[non_exhaustive]
@@ -37,12 +47,40 @@ pub enum Error {
     Busy,
     /// Not Acknowledge received
     Nack,
-    // Overrun, // slave mode only
+    /// Overrun or underrun in slave mode without clock stretching
+    Overrun,
     // Pec, // SMBUS mode only
     // Timeout, // SMBUS mode only
     // Alert, // SMBUS mode only
 }
 
+/// Address of an I2C slave
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Address {
+    /// 7-bit address
+    SevenBit(u8),
+    /// 10-bit address
+    TenBit(u16),
+}
+
+impl Address {
+    /// Sets the slave address of a master transfer
+    fn configure(self, w: &mut cr2::W) {
+        match self {
+            Address::SevenBit(addr) => {
+                crate::assert!(addr < 0x80);
+                w.add10().bit7();
+                w.sadd().bits(u16::from(addr) << 1);
+            }
+            Address::TenBit(addr) => {
+                crate::assert!(addr < 0x400);
+                w.add10().bit10();
+                w.sadd().bits(addr);
+            }
+        }
+    }
+}
+
 // FIXME these should be "closed" traits
 /// SCL pin -- DO NOT IMPLEMENT THIS TRAIT
 pub unsafe trait SclPin<I2C> {}
@@ -79,6 +117,15 @@ cfg_if! {
 }
 
 /// I2C peripheral operating in master mode
+///
+/// The embedded-hal I2C traits are implemented for 7-bit (`u8`) and 10-bit (`u16`)
+/// addresses. As both are implemented, the type of an integer literal address can't be
+/// inferred anymore and has to be given explicitly:
+///
+/// ```ignore
+/// i2c.write(0x3C_u8, &[0x00])?;
+/// i2c.write(0x2A5_u16, &[0x00])?;
+/// ```
 pub struct I2c<I2C, PINS> {
     i2c: I2C,
     pins: PINS,
@@ -108,85 +155,67 @@ macro_rules! busy_wait {
     };
 }
 
+/// Writes the timing to the disabled peripheral
+fn configure_timing(i2c: &RegisterBlock, timing: &Timing) {
+    // NOTE(write): writes all non-reserved bits.
+    i2c.timingr.write(|w| {
+        w.presc()
+            .bits(timing.presc)
+            .sdadel()
+            .bits(timing.sdadel)
+            .scldel()
+            .bits(timing.scldel)
+            .scll()
+            .bits(timing.scll)
+            .sclh()
+            .bits(timing.sclh)
+    });
+
+    // The noise filters can only be configured while the peripheral is disabled
+    i2c.cr1.modify(|_, w| {
+        w.anfoff().bit(!timing.analog_filter);
+        // NOTE(unsafe) the filter length is checked to fit into 4 bits
+        unsafe { w.dnf().bits(timing.digital_filter) }
+    });
+}
+
 impl<I2C, SCL, SDA> I2c<I2C, (SCL, SDA)> {
     /// Configures the I2C peripheral to work in master mode
-    pub fn new(i2c: I2C, pins: (SCL, SDA), freq: Hertz, clocks: Clocks, apb1: &mut APB1) -> Self
+    ///
+    /// The timing is calculated for the typical rise and fall times of the bus, see
+    /// [`timing::Config::default`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of [`Timing::new`], if no timing meeting the I2C specification
+    /// exists for the frequency.
+    pub fn new(
+        i2c: I2C,
+        pins: (SCL, SDA),
+        freq: Hertz,
+        clocks: Clocks,
+        apb1: &mut APB1,
+    ) -> Result<Self, timing::Error>
     where
         I2C: Instance,
         SCL: SclPin<I2C>,
         SDA: SdaPin<I2C>,
     {
-        crate::assert!(freq.integer() <= 1_000_000);
+        let timing = Timing::new(I2C::clock(&clocks), freq, &timing::Config::default())?;
 
+        Ok(Self::with_timing(i2c, pins, timing, apb1))
+    }
+
+    /// Configures the I2C peripheral to work in master mode with a calculated timing
+    pub fn with_timing(i2c: I2C, pins: (SCL, SDA), timing: Timing, apb1: &mut APB1) -> Self
+    where
+        I2C: Instance,
+        SCL: SclPin<I2C>,
+        SDA: SdaPin<I2C>,
+    {
         I2C::enable_clock(apb1);
 
-        // TODO review compliance with the timing requirements of I2C
-        // t_I2CCLK = 1 / PCLK1
-        // t_PRESC  = (PRESC + 1) * t_I2CCLK
-        // t_SCLL   = (SCLL + 1) * t_PRESC
-        // t_SCLH   = (SCLH + 1) * t_PRESC
-        //
-        // t_SYNC1 + t_SYNC2 > 4 * t_I2CCLK
-        // t_SCL ~= t_SYNC1 + t_SYNC2 + t_SCLL + t_SCLH
-        let i2cclk = I2C::clock(&clocks).0;
-        let ratio = i2cclk / freq.integer() - 4;
-        let (presc, scll, sclh, sdadel, scldel) = if freq.integer() >= 100_000 {
-            // fast-mode or fast-mode plus
-            // here we pick SCLL + 1 = 2 * (SCLH + 1)
-            let presc = ratio / 387;
-
-            let sclh = ((ratio / (presc + 1)) - 3) / 3;
-            let scll = 2 * (sclh + 1) - 1;
-
-            let (sdadel, scldel) = if freq.integer() > 400_000 {
-                // fast-mode plus
-                let sdadel = 0;
-                let scldel = i2cclk / 4_000_000 / (presc + 1) - 1;
-
-                (sdadel, scldel)
-            } else {
-                // fast-mode
-                let sdadel = i2cclk / 8_000_000 / (presc + 1);
-                let scldel = i2cclk / 2_000_000 / (presc + 1) - 1;
-
-                (sdadel, scldel)
-            };
-
-            (presc, scll, sclh, sdadel, scldel)
-        } else {
-            // standard-mode
-            // here we pick SCLL = SCLH
-            let presc = ratio / 514;
-
-            let sclh = ((ratio / (presc + 1)) - 2) / 2;
-            let scll = sclh;
-
-            let sdadel = i2cclk / 2_000_000 / (presc + 1);
-            let scldel = i2cclk / 800_000 / (presc + 1) - 1;
-
-            (presc, scll, sclh, sdadel, scldel)
-        };
-
-        crate::assert!(presc < 16);
-        crate::assert!(scldel < 16);
-        crate::assert!(sdadel < 16);
-        let sclh = crate::unwrap!(u8::try_from(sclh).ok());
-        let scll = crate::unwrap!(u8::try_from(scll).ok());
-
-        // Configure for "fast mode" (400 KHz)
-        // NOTE(write): writes all non-reserved bits.
-        i2c.timingr.write(|w| {
-            w.presc()
-                .bits(presc as u8)
-                .sdadel()
-                .bits(sdadel as u8)
-                .scldel()
-                .bits(scldel as u8)
-                .scll()
-                .bits(scll)
-                .sclh()
-                .bits(sclh)
-        });
+        configure_timing(&i2c, &timing);
 
         // Enable the peripheral
         i2c.cr1.modify(|_, w| w.pe().set_bit());
@@ -200,13 +229,11 @@ impl<I2C, SCL, SDA> I2c<I2C, (SCL, SDA)> {
     }
 }
 
-impl<I2C, PINS> Read for I2c<I2C, PINS>
+impl<I2C, PINS> I2c<I2C, PINS>
 where
     I2C: Instance,
 {
-    type Error = Error;
-
-    fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+    fn read_from(&mut self, addr: Address, buffer: &mut [u8]) -> Result<(), Error> {
         crate::assert!(!buffer.is_empty());
 
         // Detect Bus busy
@@ -221,9 +248,9 @@ where
             // Prepare to receive `bytes`
             self.i2c.cr2.modify(|_, w| {
                 if i == 0 {
-                    w.add10().bit7();
-                    w.sadd().bits((addr << 1) as u16);
+                    addr.configure(w);
                     w.rd_wrn().read();
+                    w.head10r().complete();
                     w.start().start();
                 }
                 w.nbytes().bits(buffer.len() as u8);
@@ -255,15 +282,8 @@ where
 
         Ok(())
     }
-}
 
-impl<I2C, PINS> Write for I2c<I2C, PINS>
-where
-    I2C: Instance,
-{
-    type Error = Error;
-
-    fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Error> {
+    fn write_to(&mut self, addr: Address, bytes: &[u8]) -> Result<(), Error> {
         // Detect Bus busy
         if self.i2c.isr.read().busy().is_busy() {
             return Err(Error::Busy);
@@ -272,8 +292,7 @@ where
         if bytes.is_empty() {
             // 0 byte write
             self.i2c.cr2.modify(|_, w| {
-                w.add10().bit7();
-                w.sadd().bits((addr << 1) as u16);
+                addr.configure(w);
                 w.rd_wrn().write();
                 w.nbytes().bits(0);
                 w.reload().completed();
@@ -288,8 +307,7 @@ where
                 // Prepare to send `bytes`
                 self.i2c.cr2.modify(|_, w| {
                     if i == 0 {
-                        w.add10().bit7();
-                        w.sadd().bits((addr << 1) as u16);
+                        addr.configure(w);
                         w.rd_wrn().write();
                         w.start().start();
                     }
@@ -326,15 +344,13 @@ where
 
         Ok(())
     }
-}
-
-impl<I2C, PINS> WriteRead for I2c<I2C, PINS>
-where
-    I2C: Instance,
-{
-    type Error = Error;
 
-    fn write_read(&mut self, addr: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
+    fn write_read_from(
+        &mut self,
+        addr: Address,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Error> {
         crate::assert!(!bytes.is_empty() && !buffer.is_empty());
 
         // Detect Bus busy
@@ -349,8 +365,7 @@ where
             // Prepare to send `bytes`
             self.i2c.cr2.modify(|_, w| {
                 if i == 0 {
-                    w.add10().bit7();
-                    w.sadd().bits((addr << 1) as u16);
+                    addr.configure(w);
                     w.rd_wrn().write();
                     w.start().start();
                 }
@@ -390,9 +405,10 @@ where
             // Prepare to receive `bytes`
             self.i2c.cr2.modify(|_, w| {
                 if i == 0 {
-                    w.add10().bit7();
-                    w.sadd().bits((addr << 1) as u16);
+                    addr.configure(w);
                     w.rd_wrn().read();
+                    // The slave is still addressed, only the header is repeated
+                    w.head10r().partial();
                     w.start().start();
                 }
                 w.nbytes().bits(buffer.len() as u8);
@@ -426,6 +442,55 @@ where
     }
 }
 
+macro_rules! blocking {
+    ($($Address:ty => $variant:ident,)+) => {
+        $(
+            impl<I2C, PINS> Read<$Address> for I2c<I2C, PINS>
+            where
+                I2C: Instance,
+            {
+                type Error = Error;
+
+                fn read(&mut self, addr: $Address, buffer: &mut [u8]) -> Result<(), Error> {
+                    self.read_from(Address::$variant(addr), buffer)
+                }
+            }
+
+            impl<I2C, PINS> Write<$Address> for I2c<I2C, PINS>
+            where
+                I2C: Instance,
+            {
+                type Error = Error;
+
+                fn write(&mut self, addr: $Address, bytes: &[u8]) -> Result<(), Error> {
+                    self.write_to(Address::$variant(addr), bytes)
+                }
+            }
+
+            impl<I2C, PINS> WriteRead<$Address> for I2c<I2C, PINS>
+            where
+                I2C: Instance,
+            {
+                type Error = Error;
+
+                fn write_read(
+                    &mut self,
+                    addr: $Address,
+                    bytes: &[u8],
+                    buffer: &mut [u8],
+                ) -> Result<(), Error> {
+                    self.write_read_from(Address::$variant(addr), bytes, buffer)
+                }
+            }
+        )+
+    };
+}
+
+blocking! {
+    SevenBitAddress => SevenBit,
+    TenBitAddress => TenBit,
+}
+
 /// I2C instance -- DO NOT IMPLEMENT THIS TRAIT
 pub unsafe trait Instance: Deref<Target = RegisterBlock> {
     #[doc(hidden)]
@@ -0,0 +1,336 @@
+//! Interrupt driven I2C master
+//!
+//! [`AsyncI2c`] starts a transfer and returns immediately. The transfer is driven by the I2C
+//! event and error interrupts, whose handlers have to call [`AsyncI2c::on_interrupt`]. Once
+//! the STOP condition has been sent, it returns the buffers together with the result.
+//!
+//! Every byte is transferred by the CPU in the interrupt handler, DMA is not used.
+//!
+//! ```no_run
+//! use stm32f3xx_hal::{
+//!     gpio::{
+//!         gpiob::{PB6, PB7},
+//!         OpenDrain, AF4,
+//!     },
+//!     i2c::{nonblocking::AsyncI2c, Address, I2c},
+//!     pac::I2C1,
+//! };
+//!
+//! type Pins = (PB6<AF4<OpenDrain>>, PB7<AF4<OpenDrain>>);
+//!
+//! fn start(i2c: I2c<I2C1, Pins>) -> AsyncI2c<I2C1, Pins, [u8; 2], [u8; 64]> {
+//!     let mut i2c = AsyncI2c::new(i2c);
+//!     i2c.write_read(Address::SevenBit(0x50), [0x00, 0x10], [0; 64]).ok();
+//!     i2c
+//! }
+//!
+//! // Called by the I2C1_EV_EXTI23 and I2C1_ER interrupt handlers
+//! fn on_interrupt(i2c: &mut AsyncI2c<I2C1, Pins, [u8; 2], [u8; 64]>) {
+//!     if let Some(done) = i2c.on_interrupt() {
+//!         if let (Ok(()), Some(data)) = (done.result, done.read_buffer) {
+//!             // process the 64 bytes of data
+//!         }
+//!     }
+//! }
+//! ```
+
+use super::{Address, Error, I2c, Instance};
+
+use crate::pac::i2c1::{cr2, RegisterBlock};
+
+/// A finished transfer
+#[derive(Debug)]
+pub struct Done<W, R> {
+    /// Result of the transfer
+    pub result: Result<(), Error>,
+    /// Buffer of the written bytes, if the transfer wrote
+    pub write_buffer: Option<W>,
+    /// Buffer of the read bytes, if the transfer read
+    pub read_buffer: Option<R>,
+}
+
+/// State of an ongoing transfer
+struct Transfer<W, R> {
+    addr: Address,
+    write_buffer: Option<W>,
+    read_buffer: Option<R>,
+    /// Whether the read phase has started
+    reading: bool,
+    /// Number of bytes of the current phase already transferred
+    pos: usize,
+    /// Error to report after the STOP condition
+    error: Option<Error>,
+}
+
+impl<W, R> Transfer<W, R> {
+    fn done(self, result: Result<(), Error>) -> Done<W, R> {
+        Done {
+            result,
+            write_buffer: self.write_buffer,
+            read_buffer: self.read_buffer,
+        }
+    }
+}
+
+/// Sets the number of bytes of the next chunk, at most 255 bytes are transferred at a time
+fn set_length(w: &mut cr2::W, remaining: usize, autoend: bool) {
+    if remaining > 0xFF {
+        w.nbytes().bits(0xFF);
+        w.reload().not_completed();
+    } else {
+        w.nbytes().bits(remaining as u8);
+        w.reload().completed();
+        if autoend {
+            w.autoend().automatic();
+        } else {
+            w.autoend().software();
+        }
+    }
+}
+
+/// Aborts any transfer by a software reset, which releases SCL and SDA and clears all flags
+fn reset(i2c: &RegisterBlock) {
+    i2c.cr1.modify(|_, w| w.pe().clear_bit());
+    // PE has to stay low for 3 APB clock cycles, which reading it back ensures
+    while i2c.cr1.read().pe().bit_is_set() {}
+    i2c.cr1.modify(|_, w| w.pe().set_bit());
+}
+
+/// I2C peripheral operating in master mode, driven by interrupts
+///
+/// The buffers are owned by the transfer, e.g. `[u8; N]` or `&'static mut [u8]`. The bytes
+/// are written from buffers of type `W` and read into buffers of type `R`.
+pub struct AsyncI2c<I2C, PINS, W, R> {
+    i2c: I2c<I2C, PINS>,
+    transfer: Option<Transfer<W, R>>,
+}
+
+impl<I2C, PINS, W, R> AsyncI2c<I2C, PINS, W, R>
+where
+    I2C: Instance,
+    W: AsRef<[u8]>,
+    R: AsMut<[u8]>,
+{
+    /// Enables the event and error interrupts of the I2C peripheral
+    pub fn new(i2c: I2c<I2C, PINS>) -> Self {
+        i2c.i2c.cr1.modify(|_, w| {
+            w.txie().enabled();
+            w.rxie().enabled();
+            w.tcie().enabled();
+            w.stopie().enabled();
+            w.nackie().enabled();
+            w.errie().enabled()
+        });
+
+        Self {
+            i2c,
+            transfer: None,
+        }
+    }
+
+    /// Disables the interrupts and releases the blocking I2C master
+    ///
+    /// # Panics
+    ///
+    /// Panics if a transfer is ongoing.
+    pub fn free(self) -> I2c<I2C, PINS> {
+        crate::assert!(self.transfer.is_none());
+
+        self.i2c.i2c.cr1.modify(|_, w| {
+            w.txie().disabled();
+            w.rxie().disabled();
+            w.tcie().disabled();
+            w.stopie().disabled();
+            w.nackie().disabled();
+            w.errie().disabled()
+        });
+
+        self.i2c
+    }
+
+    /// Returns `true` while a transfer is ongoing
+    pub fn is_busy(&self) -> bool {
+        self.transfer.is_some()
+    }
+
+    /// Starts reading `buffer.len()` bytes from the slave
+    ///
+    /// Returns the buffer with [`Error::Busy`] if the bus is busy.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is empty.
+    pub fn read(&mut self, addr: Address, mut buffer: R) -> Result<(), Done<W, R>> {
+        crate::assert!(!buffer.as_mut().is_empty());
+
+        self.start(Transfer {
+            addr,
+            write_buffer: None,
+            read_buffer: Some(buffer),
+            reading: true,
+            pos: 0,
+            error: None,
+        })
+    }
+
+    /// Starts writing the bytes of `bytes` to the slave
+    ///
+    /// Returns the buffer with [`Error::Busy`] if the bus is busy.
+    pub fn write(&mut self, addr: Address, bytes: W) -> Result<(), Done<W, R>> {
+        self.start(Transfer {
+            addr,
+            write_buffer: Some(bytes),
+            read_buffer: None,
+            reading: false,
+            pos: 0,
+            error: None,
+        })
+    }
+
+    /// Starts writing the bytes of `bytes` followed by reading `buffer.len()` bytes after a
+    /// repeated START
+    ///
+    /// Returns the buffers with [`Error::Busy`] if the bus is busy.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of the buffers is empty.
+    pub fn write_read(&mut self, addr: Address, bytes: W, mut buffer: R) -> Result<(), Done<W, R>> {
+        crate::assert!(!bytes.as_ref().is_empty() && !buffer.as_mut().is_empty());
+
+        self.start(Transfer {
+            addr,
+            write_buffer: Some(bytes),
+            read_buffer: Some(buffer),
+            reading: false,
+            pos: 0,
+            error: None,
+        })
+    }
+
+    fn start(&mut self, mut transfer: Transfer<W, R>) -> Result<(), Done<W, R>> {
+        // Detect Bus busy
+        if self.transfer.is_some() || self.i2c.i2c.isr.read().busy().is_busy() {
+            return Err(transfer.done(Err(Error::Busy)));
+        }
+
+        let addr = transfer.addr;
+        let (len, autoend) = match (&mut transfer.write_buffer, &mut transfer.read_buffer) {
+            (Some(bytes), read_buffer) => (bytes.as_ref().len(), read_buffer.is_none()),
+            (None, Some(buffer)) => (buffer.as_mut().len(), true),
+            (None, None) => crate::unreachable!(),
+        };
+
+        self.i2c.i2c.cr2.modify(|_, w| {
+            addr.configure(w);
+            if transfer.reading {
+                w.rd_wrn().read();
+                w.head10r().complete();
+            } else {
+                w.rd_wrn().write();
+            }
+            set_length(w, len, autoend);
+            w.start().start()
+        });
+
+        self.transfer = Some(transfer);
+
+        Ok(())
+    }
+
+    /// Handles the I2C event and error interrupts
+    ///
+    /// Has to be called from both interrupt handlers, or polled. Returns the buffers and the
+    /// result once the transfer is finished.
+    ///
+    /// On a bus error or an arbitration loss, the transfer is aborted by a software reset of
+    /// the peripheral, which releases the bus, before the error is returned. Pending flags
+    /// without an ongoing transfer are cleared the same way, so the interrupts don't fire
+    /// again.
+    pub fn on_interrupt(&mut self) -> Option<Done<W, R>> {
+        let i2c = &self.i2c.i2c;
+        let isr = i2c.isr.read();
+        let icr = &i2c.icr;
+
+        let transfer = match self.transfer.as_mut() {
+            Some(transfer) => transfer,
+            None => {
+                if isr.txis().is_empty()
+                    || isr.rxne().is_not_empty()
+                    || isr.tc().is_complete()
+                    || isr.tcr().is_complete()
+                    || isr.nackf().is_nack()
+                    || isr.stopf().is_stop()
+                    || isr.arlo().is_lost()
+                    || isr.berr().is_error()
+                {
+                    reset(i2c);
+                }
+                return None;
+            }
+        };
+
+        if isr.arlo().is_lost() || isr.berr().is_error() {
+            let error = if isr.arlo().is_lost() {
+                Error::Arbitration
+            } else {
+                Error::Bus
+            };
+            reset(i2c);
+            let transfer = self.transfer.take()?;
+            return Some(transfer.done(Err(error)));
+        } else if isr.nackf().is_nack() {
+            // A STOP condition is sent automatically
+            icr.write(|w| w.nackcf().clear());
+            transfer.error = Some(Error::Nack);
+        } else if isr.txis().is_empty() {
+            let bytes = crate::unwrap!(transfer.write_buffer.as_ref()).as_ref();
+            // NOTE(write): Writes all non-reserved bits.
+            i2c.txdr.write(|w| w.txdata().bits(bytes[transfer.pos]));
+            transfer.pos += 1;
+        } else if isr.rxne().is_not_empty() {
+            let buffer = crate::unwrap!(transfer.read_buffer.as_mut()).as_mut();
+            buffer[transfer.pos] = i2c.rxdr.read().rxdata().bits();
+            transfer.pos += 1;
+        } else if isr.tcr().is_complete() {
+            // Continue with the next chunk
+            let (remaining, autoend) = if transfer.reading {
+                let buffer = crate::unwrap!(transfer.read_buffer.as_mut()).as_mut();
+                (buffer.len() - transfer.pos, true)
+            } else {
+                let bytes = crate::unwrap!(transfer.write_buffer.as_ref()).as_ref();
+                (bytes.len() - transfer.pos, transfer.read_buffer.is_none())
+            };
+            i2c.cr2.modify(|_, w| {
+                set_length(w, remaining, autoend);
+                w
+            });
+        } else if isr.tc().is_complete() {
+            // The write phase of `write_read` is finished, restart for the read phase
+            let addr = transfer.addr;
+            let len = crate::unwrap!(transfer.read_buffer.as_mut()).as_mut().len();
+            transfer.reading = true;
+            transfer.pos = 0;
+            i2c.cr2.modify(|_, w| {
+                addr.configure(w);
+                w.rd_wrn().read();
+                // The slave is still addressed, only the header is repeated
+                w.head10r().partial();
+                set_length(w, len, true);
+                w.start().start()
+            });
+        } else if isr.stopf().is_stop() {
+            // Flush a byte left over after a NACK
+            i2c.isr.write(|w| w.txe().set_bit());
+            icr.write(|w| w.stopcf().clear());
+            let mut transfer = self.transfer.take()?;
+            let result = match transfer.error.take() {
+                Some(error) => Err(error),
+                None => Ok(()),
+            };
+            return Some(transfer.done(result));
+        }
+
+        None
+    }
+}
@@ -0,0 +1,344 @@
+//! I2C slave mode
+//!
+//! [`I2cSlave`] responds to its own addresses on a bus controlled by another master. The
+//! bus activity is reported as [`Status`] by [`I2cSlave::poll`], which is meant to be called
+//! from the I2C event and error interrupts after enabling them with [`I2cSlave::listen`]:
+//!
+//! ```ignore
+//! loop {
+//!     match slave.poll() {
+//!         Ok(Status::AddressMatch { direction, .. }) => { /* start of a transfer */ }
+//!         Ok(Status::Receive(byte)) => { /* byte written by the master */ }
+//!         Ok(Status::TransmitRequest) => slave.write(next_byte()),
+//!         Ok(Status::Nack) | Ok(Status::Stop) => { /* end of a transfer */ }
+//!         Err(nb::Error::Other(error)) => { /* bus error */ }
+//!         Err(nb::Error::WouldBlock) => break,
+//!     }
+//! }
+//! ```
+
+use crate::{
+    rcc::{Clocks, APB1},
+    time::rate::*,
+};
+
+use super::{
+    configure_timing,
+    timing::{self, Timing},
+    Address, Error, Instance, SclPin, SdaPin,
+};
+
+/// Number of least significant bits of the second own address, which are ignored when
+/// matching an address
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressMask {
+    /// All bits are compared
+    None,
+    /// Bit 0 is ignored
+    Bits1,
+    /// Bits 0 to 1 are ignored
+    Bits2,
+    /// Bits 0 to 2 are ignored
+    Bits3,
+    /// Bits 0 to 3 are ignored
+    Bits4,
+    /// Bits 0 to 4 are ignored
+    Bits5,
+    /// Bits 0 to 5 are ignored
+    Bits6,
+    /// All bits are ignored, every address except the reserved ones matches
+    Bits7,
+}
+
+/// Slave configuration
+#[derive(Clone, Copy, Debug)]
+pub struct Config {
+    address: Address,
+    address2: Option<(u8, AddressMask)>,
+    general_call: bool,
+    clock_stretching: bool,
+}
+
+impl Config {
+    /// Configuration responding to `address`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the address does not fit into 7 or 10 bits respectively.
+    pub fn new(address: Address) -> Self {
+        match address {
+            Address::SevenBit(address) => crate::assert!(address < 0x80),
+            Address::TenBit(address) => crate::assert!(address < 0x400),
+        }
+
+        Config {
+            address,
+            address2: None,
+            general_call: false,
+            clock_stretching: true,
+        }
+    }
+
+    /// Respond to a second 7-bit address, ignoring the bits masked by `mask`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the address does not fit into 7 bits.
+    pub fn address2(mut self, address: u8, mask: AddressMask) -> Self {
+        crate::assert!(address < 0x80);
+        self.address2 = Some((address, mask));
+        self
+    }
+
+    /// Respond to the general call address `0x00`
+    pub fn general_call(mut self, enable: bool) -> Self {
+        self.general_call = enable;
+        self
+    }
+
+    /// Stretch the clock while the slave is not ready to receive or transmit
+    ///
+    /// Without clock stretching, the byte to transmit has to be written before the master
+    /// requests it, and each received byte has to be read before the next one arrives.
+    /// Otherwise [`Error::Overrun`] is reported. Clock stretching is enabled by default.
+    ///
+    /// [`Status::TransmitRequest`] is never reported without clock stretching. The first
+    /// byte of a read has to be written with [`I2cSlave::write`] in advance, before the
+    /// [`Status::AddressMatch`] of the read is returned by [`I2cSlave::poll`], e.g. after
+    /// the [`Status::Stop`] of the previous transfer. Each further byte has to be written
+    /// as soon as the previous one has been transmitted.
+    pub fn clock_stretching(mut self, enable: bool) -> Self {
+        self.clock_stretching = enable;
+        self
+    }
+}
+
+/// Direction of a transfer as requested by the master
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// The master writes, the slave receives
+    Write,
+    /// The master reads, the slave transmits
+    Read,
+}
+
+/// Interrupt event
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// One of the own addresses matched
+    AddressMatch,
+    /// A byte has been received
+    Receive,
+    /// The master requests a byte
+    TransmitRequest,
+    /// A STOP condition has been detected
+    Stop,
+    /// The master did not acknowledge a transmitted byte
+    Nack,
+    /// Bus error, arbitration loss or overrun (error interrupt)
+    Error,
+}
+
+/// Bus activity reported by [`I2cSlave::poll`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// One of the own addresses matched, a transfer starts
+    AddressMatch {
+        /// The matched 7-bit address, or the header `0b11110XX` for the 10-bit address
+        address: u8,
+        /// Direction of the transfer
+        direction: Direction,
+    },
+    /// The master wrote a byte
+    Receive(u8),
+    /// The master reads a byte, which has to be provided with [`I2cSlave::write`]
+    TransmitRequest,
+    /// The master ended the transfer with a STOP condition
+    Stop,
+    /// The master did not acknowledge the last transmitted byte, which ends a read
+    Nack,
+}
+
+/// I2C peripheral operating in slave mode
+pub struct I2cSlave<I2C, PINS> {
+    i2c: I2C,
+    pins: PINS,
+}
+
+impl<I2C, SCL, SDA> I2cSlave<I2C, (SCL, SDA)> {
+    /// Configures the I2C peripheral to work in slave mode
+    ///
+    /// The slave does not generate the clock, but the data setup and hold times and the noise
+    /// filters are calculated for the bus frequency `freq`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of [`Timing::new`], if no timing meeting the I2C specification
+    /// exists for the frequency.
+    pub fn new(
+        i2c: I2C,
+        pins: (SCL, SDA),
+        config: Config,
+        freq: Hertz,
+        clocks: Clocks,
+        apb1: &mut APB1,
+    ) -> Result<Self, timing::Error>
+    where
+        I2C: Instance,
+        SCL: SclPin<I2C>,
+        SDA: SdaPin<I2C>,
+    {
+        let timing = Timing::new(I2C::clock(&clocks), freq, &timing::Config::default())?;
+
+        Ok(Self::with_timing(i2c, pins, config, timing, apb1))
+    }
+
+    /// Configures the I2C peripheral to work in slave mode with a calculated timing
+    pub fn with_timing(
+        i2c: I2C,
+        pins: (SCL, SDA),
+        config: Config,
+        timing: Timing,
+        apb1: &mut APB1,
+    ) -> Self
+    where
+        I2C: Instance,
+        SCL: SclPin<I2C>,
+        SDA: SdaPin<I2C>,
+    {
+        I2C::enable_clock(apb1);
+
+        configure_timing(&i2c, &timing);
+
+        // The own addresses have to be disabled while being changed
+        i2c.oar1.write(|w| w.oa1en().disabled());
+        i2c.oar1.write(|w| {
+            match config.address {
+                Address::SevenBit(address) => {
+                    w.oa1mode().bit7().oa1().bits(u16::from(address) << 1)
+                }
+                Address::TenBit(address) => w.oa1mode().bit10().oa1().bits(address),
+            };
+            w.oa1en().enabled()
+        });
+
+        i2c.oar2.write(|w| w.oa2en().disabled());
+        if let Some((address, mask)) = config.address2 {
+            i2c.oar2.write(|w| {
+                w.oa2().bits(address);
+                w.oa2msk().bits(mask as u8);
+                w.oa2en().enabled()
+            });
+        }
+
+        i2c.cr1.modify(|_, w| {
+            w.gcen().bit(config.general_call);
+            w.nostretch().bit(!config.clock_stretching)
+        });
+
+        // Enable the peripheral
+        i2c.cr1.modify(|_, w| w.pe().set_bit());
+
+        Self { i2c, pins }
+    }
+
+    /// Releases the I2C peripheral and associated pins
+    pub fn free(self) -> (I2C, (SCL, SDA)) {
+        (self.i2c, self.pins)
+    }
+}
+
+impl<I2C, PINS> I2cSlave<I2C, PINS>
+where
+    I2C: Instance,
+{
+    /// Starts listening for an interrupt event
+    pub fn listen(&mut self, event: Event) {
+        self.i2c.cr1.modify(|_, w| match event {
+            Event::AddressMatch => w.addrie().enabled(),
+            Event::Receive => w.rxie().enabled(),
+            Event::TransmitRequest => w.txie().enabled(),
+            Event::Stop => w.stopie().enabled(),
+            Event::Nack => w.nackie().enabled(),
+            Event::Error => w.errie().enabled(),
+        });
+    }
+
+    /// Stops listening for an interrupt event
+    pub fn unlisten(&mut self, event: Event) {
+        self.i2c.cr1.modify(|_, w| match event {
+            Event::AddressMatch => w.addrie().disabled(),
+            Event::Receive => w.rxie().disabled(),
+            Event::TransmitRequest => w.txie().disabled(),
+            Event::Stop => w.stopie().disabled(),
+            Event::Nack => w.nackie().disabled(),
+            Event::Error => w.errie().disabled(),
+        });
+    }
+
+    /// Returns and acknowledges the next bus activity
+    ///
+    /// Errors are reported first, followed by the bus activity in the order it happens on
+    /// the bus. Returns [`nb::Error::WouldBlock`] if nothing is pending.
+    ///
+    /// An address match is acknowledged by this method, which ends the clock stretching
+    /// after the address. When the master reads, bytes already written with
+    /// [`I2cSlave::write`] are discarded at the address match and at the STOP condition.
+    /// A [`Status::TransmitRequest`] is reported until a byte has been written.
+    ///
+    /// Without clock stretching, a byte written before the address match is kept and
+    /// transmitted first, see [`Config::clock_stretching`].
+    pub fn poll(&mut self) -> nb::Result<Status, Error> {
+        let isr = self.i2c.isr.read();
+        let icr = &self.i2c.icr;
+
+        if isr.berr().is_error() {
+            icr.write(|w| w.berrcf().clear());
+            Err(nb::Error::Other(Error::Bus))
+        } else if isr.arlo().is_lost() {
+            icr.write(|w| w.arlocf().clear());
+            Err(nb::Error::Other(Error::Arbitration))
+        } else if isr.ovr().bit_is_set() {
+            icr.write(|w| w.ovrcf().clear());
+            Err(nb::Error::Other(Error::Overrun))
+        } else if isr.addr().bit_is_set() {
+            let direction = if isr.dir().bit_is_set() {
+                // Flush a byte left over from a previous read, unless it has been written in
+                // advance as there is no clock stretching
+                if !self.i2c.cr1.read().nostretch().bit_is_set() {
+                    self.i2c.isr.write(|w| w.txe().set_bit());
+                }
+                Direction::Read
+            } else {
+                Direction::Write
+            };
+            let address = isr.addcode().bits();
+            icr.write(|w| w.addrcf().clear());
+            Ok(Status::AddressMatch { address, direction })
+        } else if isr.rxne().is_not_empty() {
+            Ok(Status::Receive(self.i2c.rxdr.read().rxdata().bits()))
+        } else if isr.txis().is_empty() {
+            Ok(Status::TransmitRequest)
+        } else if isr.nackf().is_nack() {
+            icr.write(|w| w.nackcf().clear());
+            Ok(Status::Nack)
+        } else if isr.stopf().is_stop() {
+            self.i2c.isr.write(|w| w.txe().set_bit());
+            icr.write(|w| w.stopcf().clear());
+            Ok(Status::Stop)
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Provides the next byte read by the master
+    pub fn write(&mut self, byte: u8) {
+        // NOTE(write): Writes all non-reserved bits.
+        self.i2c.txdr.write(|w| w.txdata().bits(byte));
+    }
+
+    /// Returns `true` while a transfer is ongoing on the bus
+    pub fn is_busy(&self) -> bool {
+        self.i2c.isr.read().busy().is_busy()
+    }
+}
@@ -0,0 +1,534 @@
+//! I2C timing calculation
+//!
+//! The SCL clock and the data setup and hold times are configured by the TIMINGR register.
+//! [`Timing::new`] finds register values, which meet the requirements of the I2C
+//! specification for the requested bus frequency, taking the rise and fall times of the bus
+//! and the delays of the analog and digital noise filters into account.
+//!
+//! All times are calculated in picoseconds.
+
+use core::convert::TryFrom;
+
+use crate::time::rate::*;
+
+const PS_PER_S: u64 = 1_000_000_000_000;
+
+/// Minimum input delay of the analog noise filter
+const ANALOG_FILTER_DELAY: u32 = 50_000;
+
+/// Error of the timing calculation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The bus frequency is too low or above 1 MHz
+    Frequency,
+    /// No register values meet the timing requirements of the I2C specification
+    Unachievable,
+}
+
+/// Timing requirements of an I2C speed mode
+struct Mode {
+    /// Minimum data hold time (tHD;DAT)
+    hold_min: u32,
+    /// Minimum data setup time (tSU;DAT)
+    setup_min: u32,
+    /// Minimum low period of SCL (tLOW)
+    low_min: u32,
+    /// Minimum high period of SCL (tHIGH)
+    high_min: u32,
+    /// Typical rise time
+    rise: u32,
+    /// Typical fall time
+    fall: u32,
+}
+
+const STANDARD_MODE: Mode = Mode {
+    hold_min: 0,
+    setup_min: 250_000,
+    low_min: 4_700_000,
+    high_min: 4_000_000,
+    rise: 640_000,
+    fall: 20_000,
+};
+
+const FAST_MODE: Mode = Mode {
+    hold_min: 0,
+    setup_min: 100_000,
+    low_min: 1_300_000,
+    high_min: 600_000,
+    rise: 250_000,
+    fall: 100_000,
+};
+
+const FAST_MODE_PLUS: Mode = Mode {
+    hold_min: 0,
+    setup_min: 50_000,
+    low_min: 500_000,
+    high_min: 260_000,
+    rise: 60_000,
+    fall: 100_000,
+};
+
+/// Bus characteristics and noise filter configuration
+///
+/// Use `Config::default()` for the typical rise and fall times of the speed mode, with the
+/// analog noise filter enabled and the digital noise filter disabled.
+#[derive(Clone, Copy, Debug)]
+pub struct Config {
+    /// Rise time in nanoseconds
+    rise_time: Option<u32>,
+    /// Fall time in nanoseconds
+    fall_time: Option<u32>,
+    analog_filter: bool,
+    digital_filter: u8,
+}
+
+impl Config {
+    /// Create a new `Config` using the settings of `Config::default()`.
+    pub fn new() -> Config {
+        Config::default()
+    }
+
+    /// Set the rise time of SCL and SDA, in nanoseconds.
+    pub fn rise_time(mut self, ns: u32) -> Self {
+        self.rise_time = Some(ns);
+        self
+    }
+
+    /// Set the fall time of SCL and SDA, in nanoseconds.
+    pub fn fall_time(mut self, ns: u32) -> Self {
+        self.fall_time = Some(ns);
+        self
+    }
+
+    /// Enable or disable the analog noise filter.
+    pub fn analog_filter(mut self, enable: bool) -> Self {
+        self.analog_filter = enable;
+        self
+    }
+
+    /// Set the length of the digital noise filter (DNF), in I2C clock periods. Zero
+    /// disables the filter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `periods` is larger than 15.
+    pub fn digital_filter(mut self, periods: u8) -> Self {
+        crate::assert!(periods < 16);
+        self.digital_filter = periods;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            rise_time: None,
+            fall_time: None,
+            analog_filter: true,
+            digital_filter: 0,
+        }
+    }
+}
+
+/// Values of the TIMINGR register and the noise filter configuration
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timing {
+    pub(crate) presc: u8,
+    pub(crate) scldel: u8,
+    pub(crate) sdadel: u8,
+    pub(crate) sclh: u8,
+    pub(crate) scll: u8,
+    pub(crate) analog_filter: bool,
+    pub(crate) digital_filter: u8,
+}
+
+/// Timing constraints of a bus, derived from the I2C clock and the configuration
+struct Bus {
+    /// Period of the I2C clock
+    clock: u32,
+    rise: u32,
+    fall: u32,
+    /// Minimum delay of the noise filters
+    filter_min: u32,
+    mode: &'static Mode,
+    /// Shortest allowed SCL period, which is the period of the requested frequency
+    period_min: u32,
+    /// Longest allowed SCL period, at 80 % of the requested frequency
+    period_max: u32,
+}
+
+impl Bus {
+    fn new(i2c_clock: Hertz, freq: Hertz, config: &Config) -> Result<Bus, Error> {
+        let freq = freq.integer();
+        let mode = match freq {
+            1..=100_000 => &STANDARD_MODE,
+            100_001..=400_000 => &FAST_MODE,
+            400_001..=1_000_000 => &FAST_MODE_PLUS,
+            _ => return Err(Error::Frequency),
+        };
+
+        let clock = (PS_PER_S / u64::from(i2c_clock.integer())) as u32;
+        let digital_filter = u32::from(config.digital_filter) * clock;
+        let analog_filter = if config.analog_filter {
+            ANALOG_FILTER_DELAY
+        } else {
+            0
+        };
+        let period_min = u32::try_from(PS_PER_S / u64::from(freq)).map_err(|_| Error::Frequency)?;
+        let period_max =
+            u32::try_from(u64::from(period_min) * 5 / 4).map_err(|_| Error::Frequency)?;
+
+        // The rise and fall times have to fit into the SCL period
+        let edge = |ns: Option<u32>, typical| match ns {
+            Some(ns) => u32::try_from(u64::from(ns) * 1000)
+                .ok()
+                .filter(|&ps| ps < period_min),
+            None => Some(typical),
+        };
+        let rise = edge(config.rise_time, mode.rise).ok_or(Error::Unachievable)?;
+        let fall = edge(config.fall_time, mode.fall).ok_or(Error::Unachievable)?;
+        if rise + fall >= period_min {
+            return Err(Error::Unachievable);
+        }
+
+        Ok(Bus {
+            clock,
+            rise,
+            fall,
+            filter_min: analog_filter + digital_filter,
+            mode,
+            period_min,
+            period_max,
+        })
+    }
+
+    /// SCLDEL must delay the rising SCL edge for the data setup time
+    ///
+    /// tSCLDEL = (SCLDEL + 1) * tPRESC >= tr + tSU;DAT(min)
+    fn scldel_valid(&self, presc: u8, scldel: u8) -> bool {
+        let presc = (u32::from(presc) + 1) * self.clock;
+        (u32::from(scldel) + 1) * presc >= self.rise + self.mode.setup_min
+    }
+
+    /// SDADEL must hold the data after the falling SCL edge
+    ///
+    /// tSDADEL = SDADEL * tPRESC >= tf + tHD;DAT(min) - tAF(min) - tDNF - 3 * tI2CCLK
+    ///
+    /// The maximum data valid time tVD;DAT(max) only has to be met if the SCL low period is
+    /// not stretched. The rising SCL edge always follows the data by SCLDEL, so a late data
+    /// output stretches the low period instead of violating the setup time, see
+    /// `delays_valid`.
+    fn sdadel_valid(&self, presc: u8, sdadel: u8) -> bool {
+        let delay = u32::from(sdadel) * (u32::from(presc) + 1) * self.clock;
+        let min = (self.fall + self.mode.hold_min).saturating_sub(self.filter_min + 3 * self.clock);
+
+        delay >= min
+    }
+
+    /// The data delay and setup time have to fit into the low period, otherwise the low
+    /// period gets stretched and the SCL frequency drops
+    ///
+    /// tSDADEL + tSCLDEL <= tSCLL
+    /// (SDADEL + SCLDEL + 1) * tPRESC <= (SCLL + 1) * tPRESC
+    fn delays_valid(&self, scll: u8, sdadel: u8, scldel: u8) -> bool {
+        u16::from(sdadel) + u16::from(scldel) <= u16::from(scll)
+    }
+
+    /// Duration of the low period of SCL, see `scl_period`
+    fn low(&self, presc: u8, scll: u8) -> u32 {
+        self.scl_period(self.fall, presc, scll)
+    }
+
+    /// Duration of the high period of SCL, see `scl_period`
+    fn high(&self, presc: u8, sclh: u8) -> u32 {
+        self.scl_period(self.rise, presc, sclh)
+    }
+
+    /// Duration of a low or high period of SCL, starting with the SCL `edge`
+    ///
+    /// The SCLL or SCLH counter starts once the edge is detected, after the noise filters
+    /// and the synchronization to the I2C clock (tSYNC1 and tSYNC2 in RM0316).
+    fn scl_period(&self, edge: u32, presc: u8, count: u8) -> u32 {
+        let presc = (u32::from(presc) + 1) * self.clock;
+        edge + self.filter_min + 2 * self.clock + (u32::from(count) + 1) * presc
+    }
+
+    /// Smallest SCLL or SCLH value for a low or high period of at least `duration`, starting
+    /// with the SCL `edge`
+    fn scl_count(&self, edge: u32, presc: u8, duration: u32) -> Option<u8> {
+        let presc = (u32::from(presc) + 1) * self.clock;
+        let duration = duration.saturating_sub(edge + self.filter_min + 2 * self.clock);
+        let counts = duration / presc;
+        let counts = if counts * presc < duration {
+            counts + 1
+        } else {
+            counts
+        };
+        u8::try_from(counts.saturating_sub(1)).ok()
+    }
+
+    /// The low period must last tLOW(min), and the I2C clock must be fast enough to detect
+    /// the SCL edges
+    fn low_valid(&self, low: u32) -> bool {
+        low >= self.mode.low_min && 4 * self.clock < low - self.fall - self.filter_min
+    }
+
+    /// The high period must last tHIGH(min), and the I2C clock must be fast enough to detect
+    /// the SCL edges
+    fn high_valid(&self, high: u32) -> bool {
+        high >= self.mode.high_min && self.clock < high
+    }
+
+    /// Full SCL period, the low and high periods include the rise and fall times
+    fn period(&self, low: u32, high: u32) -> u32 {
+        low.saturating_add(high)
+    }
+}
+
+impl Timing {
+    /// Calculate the timing for a bus frequency of at most `freq`, with the I2C peripheral
+    /// clocked by `i2c_clock`
+    ///
+    /// The speed mode is selected by the frequency. The calculated SCL frequency is as close
+    /// to `freq` as possible, without exceeding it. Frequencies below 80 % of `freq` are
+    /// rejected.
+    pub fn new(i2c_clock: Hertz, freq: Hertz, config: &Config) -> Result<Timing, Error> {
+        let bus = Bus::new(i2c_clock, freq, config)?;
+
+        let mut best: Option<(u32, Timing)> = None;
+        for presc in 0..16 {
+            let scldel = match (0..16).find(|&scldel| bus.scldel_valid(presc, scldel)) {
+                Some(scldel) => scldel,
+                None => continue,
+            };
+            let sdadel = match (0..16).find(|&sdadel| bus.sdadel_valid(presc, sdadel)) {
+                Some(sdadel) => sdadel,
+                None => continue,
+            };
+
+            for scll in 0..=255 {
+                let low = bus.low(presc, scll);
+                if !bus.low_valid(low) || !bus.delays_valid(scll, sdadel, scldel) {
+                    continue;
+                }
+
+                // The shortest high period, which does not exceed the frequency
+                let high = bus
+                    .mode
+                    .high_min
+                    .max(bus.clock + 1)
+                    .max(bus.period_min.saturating_sub(low));
+                let sclh = match bus.scl_count(bus.rise, presc, high) {
+                    Some(sclh) => sclh,
+                    None => continue,
+                };
+                let high = bus.high(presc, sclh);
+                if !bus.high_valid(high) {
+                    continue;
+                }
+
+                let period = bus.period(low, high);
+                if period > bus.period_max {
+                    // Longer low periods won't help either
+                    break;
+                }
+
+                let error = period - bus.period_min;
+                let better = match best {
+                    Some((best_error, _)) => error < best_error,
+                    None => true,
+                };
+                if better {
+                    best = Some((
+                        error,
+                        Timing {
+                            presc,
+                            scldel,
+                            sdadel,
+                            sclh,
+                            scll,
+                            analog_filter: config.analog_filter,
+                            digital_filter: config.digital_filter,
+                        },
+                    ));
+                }
+            }
+        }
+
+        best.map(|(_, timing)| timing).ok_or(Error::Unachievable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// SCL frequency of the timing, rounded down
+    fn frequency(i2c_clock: u32, freq: u32, timing: &Timing) -> u32 {
+        let config = Config::new()
+            .analog_filter(timing.analog_filter)
+            .digital_filter(timing.digital_filter);
+        let bus = Bus::new(Hertz(i2c_clock), Hertz(freq), &config).unwrap();
+        let low = bus.low(timing.presc, timing.scll);
+        let high = bus.high(timing.presc, timing.sclh);
+        (PS_PER_S / u64::from(bus.period(low, high))) as u32
+    }
+
+    /// The timing meets the requirements of the I2C specification
+    fn assert_valid(bus: &Bus, timing: &Timing, msg: &str) {
+        let (presc, scll, sclh) = (timing.presc, timing.scll, timing.sclh);
+        assert!(bus.scldel_valid(presc, timing.scldel), "SCLDEL of {}", msg);
+        assert!(bus.sdadel_valid(presc, timing.sdadel), "SDADEL of {}", msg);
+        assert!(
+            bus.delays_valid(scll, timing.sdadel, timing.scldel),
+            "delays of {}",
+            msg
+        );
+        assert!(bus.low_valid(bus.low(presc, scll)), "SCLL of {}", msg);
+        assert!(bus.high_valid(bus.high(presc, sclh)), "SCLH of {}", msg);
+    }
+
+    #[test]
+    fn reference_manual_examples() {
+        // Examples of timing settings from RM0316, as
+        // (I2CCLK, SCL frequency, PRESC, SCLL, SCLH, SDADEL, SCLDEL)
+        let examples = [
+            (8_000_000, 10_000, 1, 0xC7, 0xC3, 0x2, 0x4),
+            (8_000_000, 100_000, 1, 0x13, 0xF, 0x2, 0x4),
+            (8_000_000, 400_000, 0, 0x9, 0x3, 0x1, 0x3),
+            (8_000_000, 500_000, 0, 0x6, 0x3, 0x0, 0x1),
+            (16_000_000, 10_000, 3, 0xC7, 0xC3, 0x2, 0x4),
+            (16_000_000, 100_000, 3, 0x13, 0xF, 0x2, 0x4),
+            (16_000_000, 400_000, 1, 0x9, 0x3, 0x2, 0x3),
+            (16_000_000, 1_000_000, 0, 0x4, 0x2, 0x0, 0x2),
+            (48_000_000, 10_000, 0xB, 0xC7, 0xC3, 0x2, 0x4),
+            (48_000_000, 100_000, 0xB, 0x13, 0xF, 0x2, 0x4),
+            (48_000_000, 400_000, 5, 0x9, 0x3, 0x3, 0x3),
+            (48_000_000, 1_000_000, 5, 0x3, 0x1, 0x0, 0x1),
+        ];
+
+        for &(i2c_clock, freq, presc, scll, sclh, sdadel, scldel) in examples.iter() {
+            let msg = format!("{} Hz at {} Hz", freq, i2c_clock);
+            let config = Config::default();
+            let bus = Bus::new(Hertz(i2c_clock), Hertz(freq), &config).unwrap();
+            let example = Timing {
+                presc,
+                scldel,
+                sdadel,
+                sclh,
+                scll,
+                analog_filter: true,
+                digital_filter: 0,
+            };
+            assert_valid(&bus, &example, &msg);
+
+            let timing = Timing::new(Hertz(i2c_clock), Hertz(freq), &config).unwrap();
+            assert_valid(&bus, &timing, &msg);
+
+            // The calculated timing never exceeds the frequency, but stays within 5 % of it
+            let timing_freq = frequency(i2c_clock, freq, &timing);
+            assert!(timing_freq <= freq, "{}", msg);
+            assert!(timing_freq >= freq / 20 * 19, "{}", msg);
+
+            // The calculated data delays are the shortest valid ones, so they are at most as
+            // long as the delays of the example
+            let t_presc = |presc: u8| (u32::from(presc) + 1) * bus.clock;
+            assert!(
+                (u32::from(timing.scldel) + 1) * t_presc(timing.presc)
+                    <= (u32::from(scldel) + 1) * t_presc(presc),
+                "SCLDEL of {}",
+                msg
+            );
+            assert!(
+                u32::from(timing.sdadel) * t_presc(timing.presc)
+                    <= u32::from(sdadel) * t_presc(presc),
+                "SDADEL of {}",
+                msg
+            );
+        }
+    }
+
+    #[test]
+    fn sda_delay() {
+        // tSDADEL >= 300 ns - 50 ns - 3 * 20.8 ns
+        let config = Config::new().fall_time(300);
+        let bus = Bus::new(Hertz(48_000_000), Hertz(400_000), &config).unwrap();
+        assert!(!bus.sdadel_valid(0, 9));
+        assert!(bus.sdadel_valid(0, 10));
+
+        // The data delay and the setup time have to fit into the low period
+        assert!(bus.delays_valid(0x9, 0x3, 0x3));
+        assert!(!bus.delays_valid(0x9, 0x7, 0x3));
+    }
+
+    #[test]
+    fn edge_times() {
+        let config = Config::new().rise_time(u32::MAX).fall_time(u32::MAX);
+        assert_eq!(
+            Timing::new(Hertz(8_000_000), Hertz(100_000), &config),
+            Err(Error::Unachievable)
+        );
+
+        // Maximum rise and fall times of standard-mode
+        let config = Config::new().rise_time(1000).fall_time(300);
+        assert!(Timing::new(Hertz(48_000_000), Hertz(100_000), &config).is_ok());
+    }
+
+    #[test]
+    fn frequency_range() {
+        for i2c_clock in (8..=72).step_by(4).map(|mhz| mhz * 1_000_000) {
+            for &freq in [10_000, 100_000, 250_000, 400_000, 1_000_000].iter() {
+                let timing = match Timing::new(Hertz(i2c_clock), Hertz(freq), &Config::default()) {
+                    Ok(timing) => timing,
+                    // Fast-mode plus requires a fast I2C clock to detect the SCL edges
+                    Err(Error::Unachievable) if freq > 400_000 && i2c_clock < 16_000_000 => {
+                        continue
+                    }
+                    Err(error) => panic!("{:?} for {} Hz at {} Hz", error, freq, i2c_clock),
+                };
+
+                assert!(timing.presc < 16 && timing.scldel < 16 && timing.sdadel < 16);
+                let timing_freq = frequency(i2c_clock, freq, &timing);
+                assert!(timing_freq <= freq && timing_freq >= freq / 5 * 4);
+            }
+        }
+    }
+
+    #[test]
+    fn noise_filters() {
+        let config = Config::new().analog_filter(false).digital_filter(15);
+        let timing = Timing::new(Hertz(48_000_000), Hertz(400_000), &config).unwrap();
+
+        assert!(!timing.analog_filter);
+        assert_eq!(timing.digital_filter, 15);
+        assert!(frequency(48_000_000, 400_000, &timing) <= 400_000);
+    }
+
+    #[test]
+    fn invalid_frequency() {
+        let config = Config::default();
+
+        assert_eq!(
+            Timing::new(Hertz(8_000_000), Hertz(0), &config),
+            Err(Error::Frequency)
+        );
+        assert_eq!(
+            Timing::new(Hertz(8_000_000), Hertz(100), &config),
+            Err(Error::Frequency)
+        );
+        assert_eq!(
+            Timing::new(Hertz(72_000_000), Hertz(1_000_001), &config),
+            Err(Error::Frequency)
+        );
+        // The I2C clock is too slow for fast-mode plus
+        assert_eq!(
+            Timing::new(Hertz(8_000_000), Hertz(1_000_000), &config),
+            Err(Error::Unachievable)
+        );
+        // Too slow for the longest SCL period
+        assert_eq!(
+            Timing::new(Hertz(72_000_000), Hertz(1_000), &config),
+            Err(Error::Unachievable)
+        );
+    }
+}
@@ -0,0 +1,718 @@
+//! Inter-IC Sound (I2S)
+//!
+//! SPI2 and SPI3 can operate as I2S audio interfaces, which either transmit or receive. The
+//! I2S2ext and I2S3ext extensions add a second data line, which always runs in the opposite
+//! direction, for full-duplex operation (see [`I2s::with_extension`]).
+//!
+//! The I2S interfaces use the pins of the SPI peripherals:
+//!
+//! | I2S function | SPI pin        |
+//! |--------------|----------------|
+//! | CK           | [`SckPin`]     |
+//! | WS           | [`NssPin`]     |
+//! | SD           | [`MosiPin`]    |
+//! | ext_SD       | [`ExtSdPin`]   |
+//! | MCK          | [`MckPin`]     |
+//!
+//! The I2S clock is either the system clock or the clock on the I2S_CKIN pin (PC9 in
+//! alternate function 5), see [`Config::external_clock`]. The clock source is shared by both
+//! interfaces.
+//!
+//! For DMA, the interface is split into the [`spi::Tx`] and [`spi::Rx`] halves of the SPI,
+//! which support circular transfers for continuous audio streams. The extension is split into
+//! [`ExtTx`] and [`ExtRx`]. Its DMA requests share the channels of the SPI halves, which is
+//! possible as the extension always runs in the direction not used by its interface. The
+//! halves are rejoined through the [`Split`] returned with them.
+
+use core::{marker::PhantomData, ptr};
+
+use cortex_m::interrupt;
+
+#[cfg(any(feature = "stm32f303xd", feature = "stm32f303xe"))]
+use crate::gpio::{gpioa::PA10, gpiob::PB4};
+use crate::{
+    dma,
+    gpio::{
+        gpioa::{PA8, PA9},
+        gpiob::PB14,
+        gpioc::{PC11, PC6, PC7},
+        PushPull, AF5, AF6,
+    },
+    pac::{spi1, I2S2EXT, I2S3EXT, RCC, SPI2, SPI3},
+    rcc::{Clocks, APB1},
+    spi::{self, MosiPin, NssPin, Polarity, SckPin},
+    time::rate::*,
+};
+
+/// I2S error
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Overrun occurred, received data was lost
+    Overrun,
+    /// Underrun occurred, the slave transmitter had no data when a frame started
+    Underrun,
+    /// Frame error, the WS signal changed at an unexpected time in slave mode
+    FrameFormat,
+}
+
+// FIXME these should be "closed" traits
+/// MCK pin -- DO NOT IMPLEMENT THIS TRAIT
+pub unsafe trait MckPin<SPI> {
+    #[doc(hidden)]
+    const ENABLED: bool = true;
+}
+
+/// Placeholder for the MCK pin, if no master clock is output
+pub struct NoMck;
+
+unsafe impl MckPin<SPI2> for NoMck {
+    const ENABLED: bool = false;
+}
+unsafe impl MckPin<SPI3> for NoMck {
+    const ENABLED: bool = false;
+}
+
+unsafe impl MckPin<SPI2> for PA8<AF5<PushPull>> {}
+unsafe impl MckPin<SPI2> for PC6<AF6<PushPull>> {}
+
+unsafe impl MckPin<SPI3> for PA9<AF5<PushPull>> {}
+unsafe impl MckPin<SPI3> for PC7<AF6<PushPull>> {}
+
+/// ext_SD pin of an I2S extension -- DO NOT IMPLEMENT THIS TRAIT
+///
+/// These are the MISO pins of the SPI peripheral. Unlike [`spi::MisoPin`], there is no
+/// placeholder, as the extension can not operate without its data line.
+pub unsafe trait ExtSdPin<SPI> {}
+
+#[cfg(any(feature = "stm32f303xd", feature = "stm32f303xe"))]
+unsafe impl ExtSdPin<SPI2> for PA10<AF5<PushPull>> {}
+unsafe impl ExtSdPin<SPI2> for PB14<AF5<PushPull>> {}
+
+#[cfg(any(feature = "stm32f303xd", feature = "stm32f303xe"))]
+unsafe impl ExtSdPin<SPI3> for PB4<AF6<PushPull>> {}
+unsafe impl ExtSdPin<SPI3> for PC11<AF6<PushPull>> {}
+
+/// Operating mode of the I2S interface
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Generates the clock and transmits
+    MasterTransmit,
+    /// Generates the clock and receives
+    MasterReceive,
+    /// Transmits with the clock of another master
+    SlaveTransmit,
+    /// Receives with the clock of another master
+    SlaveReceive,
+}
+
+/// I2S standard
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Standard {
+    /// Philips I2S standard, the data is delayed by one clock cycle to WS
+    Philips,
+    /// MSB justified standard
+    MsbJustified,
+    /// LSB justified standard
+    LsbJustified,
+    /// PCM standard with a WS pulse of one clock cycle
+    PcmShort,
+    /// PCM standard with a WS pulse of 13 clock cycles
+    PcmLong,
+}
+
+/// Length of the data and of the channel containing it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataFormat {
+    /// 16-bit data in a 16-bit channel
+    Data16Channel16,
+    /// 16-bit data in a 32-bit channel
+    Data16Channel32,
+    /// 24-bit data in a 32-bit channel
+    Data24Channel32,
+    /// 32-bit data in a 32-bit channel
+    Data32Channel32,
+}
+
+/// Audio channel of a transmitted or received half-word
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channel {
+    /// Left channel
+    Left,
+    /// Right channel
+    Right,
+}
+
+/// I2S configuration
+///
+/// Use `Config::default()` for a master transmitter with the Philips standard, 16-bit data,
+/// an idle low clock and a sample rate of 48 kHz, clocked by the system clock.
+pub struct Config {
+    mode: Mode,
+    standard: Standard,
+    data_format: DataFormat,
+    polarity: Polarity,
+    sample_rate: Hertz,
+    external_clock: Option<Hertz>,
+}
+
+impl Config {
+    /// Create a new `Config` using the settings of `Config::default()`.
+    pub fn new() -> Config {
+        Config::default()
+    }
+
+    /// Set the operating mode.
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set the I2S standard.
+    pub fn standard(mut self, standard: Standard) -> Self {
+        self.standard = standard;
+        self
+    }
+
+    /// Set the data and channel length.
+    pub fn data_format(mut self, data_format: DataFormat) -> Self {
+        self.data_format = data_format;
+        self
+    }
+
+    /// Set the idle level of the clock.
+    pub fn polarity(mut self, polarity: Polarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    /// Set the sample rate, which is only used in master mode.
+    pub fn sample_rate(mut self, sample_rate: Hertz) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    /// Use the clock with the given frequency on the I2S_CKIN pin instead of the system
+    /// clock.
+    pub fn external_clock(mut self, frequency: Hertz) -> Self {
+        self.external_clock = Some(frequency);
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            mode: Mode::MasterTransmit,
+            standard: Standard::Philips,
+            data_format: DataFormat::Data16Channel16,
+            polarity: Polarity::IdleLow,
+            sample_rate: Hertz(48_000),
+            external_clock: None,
+        }
+    }
+}
+
+/// Returns the I2S clock divider (I2SDIV and ODD) for the sample rate, together with the
+/// actually achieved sample rate
+///
+/// Each sample is made of two channels. With the master clock output enabled, the master
+/// clock runs at 256 times the sample rate, independent of the channel length.
+fn clock_divider(
+    i2s_clock: u32,
+    sample_rate: u32,
+    data_format: DataFormat,
+    master_clock: bool,
+) -> (u8, bool, Hertz) {
+    let clocks_per_sample = if master_clock {
+        256
+    } else if data_format == DataFormat::Data16Channel16 {
+        2 * 16
+    } else {
+        2 * 32
+    };
+
+    let divider = clocks_per_sample * sample_rate;
+    let divider = (i2s_clock + divider / 2) / divider;
+    // I2SDIV values of 0 and 1 are forbidden
+    crate::assert!(
+        (4..512).contains(&divider),
+        "sample rate is not achievable with the I2S clock"
+    );
+
+    (
+        (divider / 2) as u8,
+        divider % 2 == 1,
+        Hertz(i2s_clock / (clocks_per_sample * divider)),
+    )
+}
+
+/// Writes the I2S configuration register, with the I2S still disabled
+fn configure(i2s: &spi1::RegisterBlock, config: &Config) {
+    // I2SMOD: I2S mode
+    // I2SCFG: operating mode
+    // I2SSTD: standard
+    // PCMSYNC: PCM frame synchronization
+    // CKPOL: clock polarity
+    // DATLEN: data length
+    // CHLEN: channel length
+    i2s.i2scfgr.write(|w| {
+        w.i2smod().i2smode();
+
+        match config.mode {
+            Mode::MasterTransmit => w.i2scfg().master_tx(),
+            Mode::MasterReceive => w.i2scfg().master_rx(),
+            Mode::SlaveTransmit => w.i2scfg().slave_tx(),
+            Mode::SlaveReceive => w.i2scfg().slave_rx(),
+        };
+
+        match config.standard {
+            Standard::Philips => w.i2sstd().philips(),
+            Standard::MsbJustified => w.i2sstd().msb(),
+            Standard::LsbJustified => w.i2sstd().lsb(),
+            Standard::PcmShort => w.i2sstd().pcm().pcmsync().short(),
+            Standard::PcmLong => w.i2sstd().pcm().pcmsync().long(),
+        };
+
+        match config.polarity {
+            Polarity::IdleLow => w.ckpol().idle_low(),
+            Polarity::IdleHigh => w.ckpol().idle_high(),
+        };
+
+        match config.data_format {
+            DataFormat::Data16Channel16 => w.datlen().sixteen_bit().chlen().sixteen_bit(),
+            DataFormat::Data16Channel32 => w.datlen().sixteen_bit().chlen().thirty_two_bit(),
+            DataFormat::Data24Channel32 => w.datlen().twenty_four_bit().chlen().thirty_two_bit(),
+            DataFormat::Data32Channel32 => w.datlen().thirty_two_bit().chlen().thirty_two_bit(),
+        }
+    });
+}
+
+/// Reads a half-word, if one was received
+fn read(i2s: &spi1::RegisterBlock) -> nb::Result<u16, Error> {
+    let sr = i2s.sr.read();
+
+    Err(if sr.ovr().is_overrun() {
+        // The overrun flag is cleared by reading DR and then SR
+        let _ = i2s.dr.read();
+        let _ = i2s.sr.read();
+        nb::Error::Other(Error::Overrun)
+    } else if sr.fre().bit_is_set() {
+        // The frame error flag is cleared by reading SR
+        nb::Error::Other(Error::FrameFormat)
+    } else if sr.rxne().is_not_empty() {
+        // NOTE(unsafe) 16-bit read from a register owned by the I2S
+        return Ok(unsafe { ptr::read_volatile(&i2s.dr as *const _ as *const u16) });
+    } else {
+        nb::Error::WouldBlock
+    })
+}
+
+/// Writes a half-word, if the transmit buffer is empty
+fn write(i2s: &spi1::RegisterBlock, data: u16) -> nb::Result<(), Error> {
+    let sr = i2s.sr.read();
+
+    Err(if sr.udr().bit_is_set() {
+        // The underrun flag is cleared by reading SR
+        nb::Error::Other(Error::Underrun)
+    } else if sr.fre().bit_is_set() {
+        nb::Error::Other(Error::FrameFormat)
+    } else if sr.txe().is_empty() {
+        // NOTE(unsafe) 16-bit write to a register owned by the I2S
+        unsafe { ptr::write_volatile(&i2s.dr as *const _ as *mut u16, data) };
+        return Ok(());
+    } else {
+        nb::Error::WouldBlock
+    })
+}
+
+/// Channel of the half-word to be transmitted next or received last
+fn channel(i2s: &spi1::RegisterBlock) -> Channel {
+    if i2s.sr.read().chside().bit_is_set() {
+        Channel::Right
+    } else {
+        Channel::Left
+    }
+}
+
+/// I2S interface on top of an SPI peripheral
+///
+/// Data is transferred in half-words. 24-bit and 32-bit data is transferred as two
+/// half-words, the most significant one first. 24-bit data is left aligned, so the lowest
+/// 8 bits of the second half-word are zero.
+pub struct I2s<SPI, PINS> {
+    spi: SPI,
+    pins: PINS,
+    sample_rate: Option<Hertz>,
+}
+
+/// Extension of an [`I2s`] interface for full-duplex operation
+///
+/// The extension always runs as slave of its interface, in the opposite direction.
+pub struct I2sExt<EXT, PIN> {
+    ext: EXT,
+    pin: PIN,
+}
+
+/// Transmitting half of an [`I2sExt`], used for DMA transfers
+pub struct ExtTx<EXT> {
+    _ext: PhantomData<EXT>,
+}
+
+/// Receiving half of an [`I2sExt`], used for DMA transfers
+pub struct ExtRx<EXT> {
+    _ext: PhantomData<EXT>,
+}
+
+/// I2S interface or extension, whose transmitter and receiver have been split off
+///
+/// Returned by [`I2s::split`](I2s#method.split) and [`I2sExt::split`](I2sExt#method.split).
+/// Owns the peripheral and the pins until the halves are rejoined with `join`.
+pub struct Split<T> {
+    driver: T,
+}
+
+macro_rules! hal {
+    ($($SPIX:ident: ($spiX:ident, $spiXen:ident, $spiXrst:ident, $I2SXEXT:ident),)+) => {
+        $(
+            impl<CK, WS, SD, MCK> I2s<$SPIX, (CK, WS, SD, MCK)> {
+                /// Configures the SPI peripheral to operate as I2S interface
+                ///
+                /// Pass [`NoMck`] as MCK pin, if no master clock output is required.
+                ///
+                /// # Panics
+                ///
+                /// Panics in master mode, if the sample rate can not be derived from the I2S
+                /// clock.
+                pub fn $spiX(
+                    spi: $SPIX,
+                    pins: (CK, WS, SD, MCK),
+                    config: Config,
+                    clocks: Clocks,
+                    apb1: &mut APB1,
+                ) -> Self
+                where
+                    CK: SckPin<$SPIX>,
+                    WS: NssPin<$SPIX>,
+                    SD: MosiPin<$SPIX>,
+                    MCK: MckPin<$SPIX>,
+                {
+                    // enable or reset $SPIX
+                    apb1.enr().modify(|_, w| w.$spiXen().enabled());
+                    apb1.rstr().modify(|_, w| w.$spiXrst().reset());
+                    apb1.rstr().modify(|_, w| w.$spiXrst().clear_bit());
+
+                    let i2s_clock = match config.external_clock {
+                        Some(frequency) => frequency,
+                        None => clocks.sysclk(),
+                    };
+                    cortex_m::interrupt::free(|_| {
+                        // NOTE(unsafe) atomic read-modify-write of a register, which
+                        // configures the clocks of other peripherals
+                        let rcc = unsafe { &*RCC::ptr() };
+                        rcc.cfgr
+                            .modify(|_, w| w.i2ssrc().bit(config.external_clock.is_some()));
+                    });
+
+                    let master = matches!(config.mode, Mode::MasterTransmit | Mode::MasterReceive);
+                    let sample_rate = if master {
+                        let (div, odd, sample_rate) = clock_divider(
+                            i2s_clock.0,
+                            config.sample_rate.0,
+                            config.data_format,
+                            MCK::ENABLED,
+                        );
+                        // I2SDIV: linear prescaler
+                        // ODD: odd factor of the prescaler
+                        // MCKOE: master clock output
+                        // NOTE(unsafe) the prescaler is checked to fit into 8 bits
+                        spi.i2spr.write(|w| unsafe {
+                            w.i2sdiv().bits(div);
+                            w.odd().bit(odd);
+                            w.mckoe().bit(MCK::ENABLED)
+                        });
+                        Some(sample_rate)
+                    } else {
+                        None
+                    };
+
+                    configure(&spi, &config);
+                    spi.i2scfgr.modify(|_, w| w.i2se().enabled());
+
+                    I2s { spi, pins, sample_rate }
+                }
+
+                /// Adds the extension for full-duplex operation
+                ///
+                /// The extension is clocked by this interface and transfers data in the
+                /// opposite direction on the ext_SD pin.
+                pub fn with_extension<EXTSD>(
+                    self,
+                    ext: $I2SXEXT,
+                    ext_sd: EXTSD,
+                ) -> (Self, I2sExt<$I2SXEXT, EXTSD>)
+                where
+                    EXTSD: ExtSdPin<$SPIX>,
+                {
+                    // The extension has to be ready before the clock starts
+                    self.spi.i2scfgr.modify(|_, w| w.i2se().disabled());
+
+                    let cfgr = self.spi.i2scfgr.read();
+                    // I2SCFG: the lower bit selects reception
+                    let transmit = cfgr.i2scfg().bits() & 1 == 0;
+                    // NOTE(unsafe) copy of a valid configuration
+                    ext.i2scfgr.write(|w| unsafe { w.bits(cfgr.bits()) });
+                    ext.i2scfgr.modify(|_, w| {
+                        if transmit {
+                            w.i2scfg().slave_rx();
+                        } else {
+                            w.i2scfg().slave_tx();
+                        }
+                        w.i2se().enabled()
+                    });
+
+                    self.spi.i2scfgr.modify(|_, w| w.i2se().enabled());
+
+                    (self, I2sExt { ext, pin: ext_sd })
+                }
+
+                /// Releases the SPI peripheral and associated pins
+                pub fn free(self) -> ($SPIX, (CK, WS, SD, MCK)) {
+                    self.spi.i2scfgr.modify(|_, w| w.i2se().disabled());
+                    (self.spi, self.pins)
+                }
+            }
+
+            impl<PINS> I2s<$SPIX, PINS> {
+                /// The actual sample rate in master mode, which may deviate slightly from the
+                /// configured one
+                pub fn sample_rate(&self) -> Option<Hertz> {
+                    self.sample_rate
+                }
+
+                /// Read a received half-word
+                pub fn read(&mut self) -> nb::Result<u16, Error> {
+                    read(&self.spi)
+                }
+
+                /// Write a half-word to be transmitted
+                pub fn write(&mut self, data: u16) -> nb::Result<(), Error> {
+                    write(&self.spi, data)
+                }
+
+                /// Channel of the half-word to be transmitted next or received last
+                ///
+                /// This is not defined for the PCM standards.
+                pub fn channel(&self) -> Channel {
+                    channel(&self.spi)
+                }
+
+                /// Splits the `I2s` abstraction into a transmitter and a receiver half
+                ///
+                /// The halves are used for DMA transfers, in the direction of the configured
+                /// mode. The peripheral and the pins are kept in the returned [`Split`] until
+                /// the halves are rejoined.
+                pub fn split(self) -> (spi::Tx<$SPIX, u16>, spi::Rx<$SPIX, u16>, Split<Self>) {
+                    (spi::Tx::new(), spi::Rx::new(), Split { driver: self })
+                }
+            }
+
+            impl<PINS> Split<I2s<$SPIX, PINS>> {
+                /// Rejoins the transmitter and the receiver with the `I2s`
+                pub fn join(
+                    self,
+                    _tx: spi::Tx<$SPIX, u16>,
+                    _rx: spi::Rx<$SPIX, u16>,
+                ) -> I2s<$SPIX, PINS> {
+                    self.driver
+                }
+            }
+
+            impl<CK, WS, SD, MCK> Split<I2s<$SPIX, (CK, WS, SD, MCK)>> {
+                /// Rejoins the transmitter and the receiver and releases the SPI peripheral
+                /// and associated pins
+                pub fn free(
+                    self,
+                    tx: spi::Tx<$SPIX, u16>,
+                    rx: spi::Rx<$SPIX, u16>,
+                ) -> ($SPIX, (CK, WS, SD, MCK)) {
+                    self.join(tx, rx).free()
+                }
+            }
+
+            impl<PIN> I2sExt<$I2SXEXT, PIN> {
+                /// Read a received half-word
+                pub fn read(&mut self) -> nb::Result<u16, Error> {
+                    read(&self.ext)
+                }
+
+                /// Write a half-word to be transmitted
+                pub fn write(&mut self, data: u16) -> nb::Result<(), Error> {
+                    write(&self.ext, data)
+                }
+
+                /// Channel of the half-word to be transmitted next or received last
+                pub fn channel(&self) -> Channel {
+                    channel(&self.ext)
+                }
+
+                /// Splits the extension into a transmitter and a receiver half
+                ///
+                /// The halves are used for DMA transfers, in the direction opposite to the
+                /// interface. The extension and its pin are kept in the returned [`Split`]
+                /// until the halves are rejoined.
+                pub fn split(self) -> (ExtTx<$I2SXEXT>, ExtRx<$I2SXEXT>, Split<Self>) {
+                    (
+                        ExtTx { _ext: PhantomData },
+                        ExtRx { _ext: PhantomData },
+                        Split { driver: self },
+                    )
+                }
+
+                /// Releases the extension and its pin
+                pub fn free(self) -> ($I2SXEXT, PIN) {
+                    self.ext.i2scfgr.modify(|_, w| w.i2se().disabled());
+                    (self.ext, self.pin)
+                }
+            }
+
+            impl<PIN> Split<I2sExt<$I2SXEXT, PIN>> {
+                /// Rejoins the transmitter and the receiver with the `I2sExt`
+                pub fn join(
+                    self,
+                    _tx: ExtTx<$I2SXEXT>,
+                    _rx: ExtRx<$I2SXEXT>,
+                ) -> I2sExt<$I2SXEXT, PIN> {
+                    self.driver
+                }
+
+                /// Rejoins the transmitter and the receiver and releases the extension and
+                /// its pin
+                pub fn free(self, tx: ExtTx<$I2SXEXT>, rx: ExtRx<$I2SXEXT>) -> ($I2SXEXT, PIN) {
+                    self.join(tx, rx).free()
+                }
+            }
+
+            impl ExtTx<$I2SXEXT> {
+                /// Transmit all half-words of the buffer using DMA.
+                pub fn write_all<B, C>(
+                    self,
+                    buffer: B,
+                    mut channel: C
+                ) -> dma::Transfer<B, C, Self>
+                where
+                    Self: dma::OnChannel<C>,
+                    B: dma::ReadBuffer<Word = u16> + 'static,
+                    C: dma::Channel,
+                {
+                    // NOTE(unsafe) taking the address of a register
+                    let pa = unsafe { &(*$I2SXEXT::ptr()).dr } as *const _ as u32;
+                    // NOTE(unsafe) usage of a valid peripheral address
+                    unsafe { channel.set_peripheral_address(pa, dma::Increment::Disable) };
+
+                    dma::Transfer::start_read(buffer, channel, self)
+                }
+
+                /// Continuously transmit the buffer using DMA, wrapping around at its end.
+                pub fn write_circular<B, C>(
+                    self,
+                    buffer: B,
+                    mut channel: C
+                ) -> dma::CircTransfer<B, C, Self>
+                where
+                    Self: dma::OnChannel<C>,
+                    B: dma::ReadBuffer<Word = u16> + 'static,
+                    C: dma::Channel,
+                {
+                    // NOTE(unsafe) taking the address of a register
+                    let pa = unsafe { &(*$I2SXEXT::ptr()).dr } as *const _ as u32;
+                    // NOTE(unsafe) usage of a valid peripheral address
+                    unsafe { channel.set_peripheral_address(pa, dma::Increment::Disable) };
+
+                    dma::CircTransfer::start_read(buffer, channel, self)
+                }
+            }
+
+            impl ExtRx<$I2SXEXT> {
+                /// Fill the buffer with received half-words using DMA.
+                pub fn read_exact<B, C>(
+                    self,
+                    buffer: B,
+                    mut channel: C
+                ) -> dma::Transfer<B, C, Self>
+                where
+                    Self: dma::OnChannel<C>,
+                    B: dma::WriteBuffer<Word = u16> + 'static,
+                    C: dma::Channel,
+                {
+                    // NOTE(unsafe) taking the address of a register
+                    let pa = unsafe { &(*$I2SXEXT::ptr()).dr } as *const _ as u32;
+                    // NOTE(unsafe) usage of a valid peripheral address
+                    unsafe { channel.set_peripheral_address(pa, dma::Increment::Disable) };
+
+                    dma::Transfer::start_write(buffer, channel, self)
+                }
+
+                /// Continuously receive into the buffer using DMA, wrapping around at its end.
+                pub fn read_circular<B, C>(
+                    self,
+                    buffer: B,
+                    mut channel: C
+                ) -> dma::CircTransfer<B, C, Self>
+                where
+                    Self: dma::OnChannel<C>,
+                    B: dma::WriteBuffer<Word = u16> + 'static,
+                    C: dma::Channel,
+                {
+                    // NOTE(unsafe) taking the address of a register
+                    let pa = unsafe { &(*$I2SXEXT::ptr()).dr } as *const _ as u32;
+                    // NOTE(unsafe) usage of a valid peripheral address
+                    unsafe { channel.set_peripheral_address(pa, dma::Increment::Disable) };
+
+                    dma::CircTransfer::start_write(buffer, channel, self)
+                }
+            }
+
+            impl dma::Target for ExtTx<$I2SXEXT> {
+                fn enable_dma(&mut self) {
+                    // NOTE(unsafe) critical section prevents races
+                    interrupt::free(|_| unsafe {
+                        let cr2 = &(*$I2SXEXT::ptr()).cr2;
+                        cr2.modify(|_, w| w.txdmaen().enabled());
+                    });
+                }
+
+                fn disable_dma(&mut self) {
+                    // NOTE(unsafe) critical section prevents races
+                    interrupt::free(|_| unsafe {
+                        let cr2 = &(*$I2SXEXT::ptr()).cr2;
+                        cr2.modify(|_, w| w.txdmaen().disabled());
+                    });
+                }
+            }
+
+            impl dma::Target for ExtRx<$I2SXEXT> {
+                fn enable_dma(&mut self) {
+                    // NOTE(unsafe) critical section prevents races
+                    interrupt::free(|_| unsafe {
+                        let cr2 = &(*$I2SXEXT::ptr()).cr2;
+                        cr2.modify(|_, w| w.rxdmaen().enabled());
+                    });
+                }
+
+                fn disable_dma(&mut self) {
+                    // NOTE(unsafe) critical section prevents races
+                    interrupt::free(|_| unsafe {
+                        let cr2 = &(*$I2SXEXT::ptr()).cr2;
+                        cr2.modify(|_, w| w.rxdmaen().disabled());
+                    });
+                }
+            }
+        )+
+    }
+}
+
+hal! {
+    SPI2: (spi2, spi2en, spi2rst, I2S2EXT),
+    SPI3: (spi3, spi3en, spi3rst, I2S3EXT),
+}
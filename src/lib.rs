@@ -85,7 +85,7 @@
  [defmt]: https://github.com/knurling-rs/defmt
  [filter]: https://defmt.ferrous-systems.com/filtering.html
 */
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 #![allow(non_camel_case_types)]
 #![allow(clippy::upper_case_acronyms)]
 #![warn(missing_docs)]
@@ -137,6 +137,22 @@ pub mod dma;
 pub mod flash;
 pub mod gpio;
 pub mod i2c;
+#[cfg(any(
+    feature = "stm32f303xb",
+    feature = "stm32f303xc",
+    feature = "stm32f303xd",
+    feature = "stm32f303xe",
+))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(
+        feature = "stm32f303xb",
+        feature = "stm32f303xc",
+        feature = "stm32f303xd",
+        feature = "stm32f303xe",
+    )))
+)]
+pub mod i2s;
 pub mod prelude;
 pub mod pwm;
 pub mod rcc;
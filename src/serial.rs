@@ -1,17 +1,25 @@
 //! Serial
 
-use core::{convert::Infallible, marker::PhantomData, ptr};
+use core::{convert::Infallible, marker::PhantomData, ops::Deref, ptr};
 
 use crate::{
-    gpio::{gpioa, gpiob, gpioc, AF7},
-    hal::{blocking, serial},
-    pac::{USART1, USART2, USART3},
+    gpio::{gpioa, gpiob, gpioc, OpenDrain, Pin, AF7},
+    hal::{
+        blocking, serial,
+        spi::{FullDuplex, Mode, Phase, Polarity},
+    },
+    pac::{usart1::RegisterBlock, EXTI, RCC, USART1, USART2, USART3},
     rcc::{Clocks, APB1, APB2},
     time::rate::*,
 };
 
 use cfg_if::cfg_if;
 
+pub mod buffered;
+pub mod irda;
+pub mod lin;
+pub mod smartcard;
+
 cfg_if! {
     if #[cfg(any(feature = "stm32f302", feature = "stm32f303"))] {
         use crate::dma;
@@ -27,6 +35,16 @@ pub enum Event {
     Txe,
     /// Transmission complete
     Tc,
+    /// The receive line went idle after receiving data
+    Idle,
+    /// The receive line stayed idle for the configured receiver timeout
+    ReceiverTimeout,
+    /// The configured match character has been received
+    CharacterMatch,
+    /// A framing, noise or overrun error occurred while receiving with DMA
+    Error,
+    /// A parity error occurred
+    ParityError,
 }
 
 /// Serial error
@@ -41,14 +59,256 @@ pub enum Error {
     Overrun,
     /// Parity check error
     Parity,
+    /// Automatic baud rate detection failed
+    AutoBaudRate,
+}
+
+/// Driver enable (DE) signal polarity
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DePolarity {
+    /// DE is high while the transceiver drives the bus
+    ActiveHigh,
+    /// DE is low while the transceiver drives the bus
+    ActiveLow,
+}
+
+/// RS-485 driver enable configuration
+///
+/// The assertion and deassertion times are given in sample times. A sample time is 1/16 of a
+/// bit time, as the USART is always configured for 16 times oversampling (it would be 1/8
+/// with 8 times oversampling). Both are limited to 5 bits (`0..=31`), that is just below two
+/// bit times.
+///
+/// Use `Rs485Config::default()` for an active high DE signal without any extra delays.
+pub struct Rs485Config {
+    polarity: DePolarity,
+    assertion_samples: u8,
+    deassertion_samples: u8,
+}
+
+impl Rs485Config {
+    /// Create a new `Rs485Config` using the settings of `Rs485Config::default()`.
+    pub fn new() -> Rs485Config {
+        Rs485Config::default()
+    }
+
+    /// Set the polarity of the DE signal.
+    pub fn polarity(mut self, polarity: DePolarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    /// Set the time between the activation of DE and the start bit of the first character
+    /// (DEAT), in sample times of 1/16 bit time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `samples` is larger than 31.
+    pub fn assertion_samples(mut self, samples: u8) -> Self {
+        crate::assert!(samples < 32);
+        self.assertion_samples = samples;
+        self
+    }
+
+    /// Set the time between the end of the last stop bit and the deactivation of DE (DEDT),
+    /// in sample times of 1/16 bit time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `samples` is larger than 31.
+    pub fn deassertion_samples(mut self, samples: u8) -> Self {
+        crate::assert!(samples < 32);
+        self.deassertion_samples = samples;
+        self
+    }
+}
+
+impl Default for Rs485Config {
+    fn default() -> Self {
+        Rs485Config {
+            polarity: DePolarity::ActiveHigh,
+            assertion_samples: 0,
+            deassertion_samples: 0,
+        }
+    }
+}
+
+/// Returns the most severe pending reception error, if any, and clears its flag
+fn take_error(usart: &RegisterBlock) -> Option<Error> {
+    let isr = usart.isr.read();
+    let icr = &usart.icr;
+
+    if isr.pe().bit_is_set() {
+        icr.write(|w| w.pecf().clear());
+        Some(Error::Parity)
+    } else if isr.fe().bit_is_set() {
+        icr.write(|w| w.fecf().clear());
+        Some(Error::Framing)
+    } else if isr.nf().bit_is_set() {
+        icr.write(|w| w.ncf().clear());
+        Some(Error::Noise)
+    } else if isr.ore().bit_is_set() {
+        icr.write(|w| w.orecf().clear());
+        Some(Error::Overrun)
+    } else {
+        None
+    }
+}
+
+/// Clears all reception error flags
+fn clear_errors(usart: &RegisterBlock) {
+    usart.icr.write(|w| {
+        w.pecf().clear();
+        w.fecf().clear();
+        w.ncf().clear();
+        w.orecf().clear()
+    });
+}
+
+/// Method used for automatic baud rate detection
+///
+/// The baud rate is measured on the first received character(s), which therefore
+/// have to follow a known pattern.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AutoBaudMode {
+    /// Measure the start bit, the character has to start with a 1 bit
+    StartBit,
+    /// Measure from the first falling edge to the next one, the character has to start
+    /// with `10xx`
+    FallingEdge,
+    /// Measure a `0x7F` character
+    Frame0x7F,
+    /// Measure a `0x55` character
+    Frame0x55,
+}
+
+/// Condition to leave mute mode
+///
+/// Address characters are marked by their most significant bit being set, the remaining bits
+/// hold the address. On a 9-bit multidrop bus, the USART is configured for
+/// [`WordLength::DataBits9`], so the 9th bit is the address mark and the data characters
+/// still carry 8 bits. The address field is shared with the match character, see
+/// [`Serial::set_match_character`](Serial#method.set_match_character).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Wakeup {
+    /// Wake up when the receive line goes idle
+    IdleLine,
+    /// Wake up on an address character, whose 4 least significant bits match the address
+    Address4(u8),
+    /// Wake up on an address character, whose 7 least significant bits match the address
+    Address7(u8),
+}
+
+/// Clock of the USART, which keeps running in Stop mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopModeClock {
+    /// The 8 MHz high speed internal oscillator, which is started on demand
+    Hsi,
+    /// The 32.768 kHz low speed external oscillator, which has to be enabled already
+    Lse,
+}
+
+/// USART event, which wakes up the MCU from Stop mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WakeupEvent {
+    /// A start bit has been detected
+    StartBit,
+    /// A character has been received
+    Rxne,
+    /// An address character matching the configured address has been received, see
+    /// [`Wakeup`]
+    AddressMatch,
+}
+
+/// Number of data bits of a character, including the parity bit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WordLength {
+    /// 7 data bits
+    DataBits7,
+    /// 8 data bits, the default
+    DataBits8,
+    /// 9 data bits, which are read and written with `read_word` and `write_word`
+    DataBits9,
+}
+
+/// Bit order of the transmitted and received data
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BitOrder {
+    /// Least significant bit first, the standard for UART communication
+    LsbFirst,
+    /// Most significant bit first
+    MsbFirst,
+}
+
+/// Pin used with swapped TX and RX functions (type state)
+///
+/// Wrapping the pins in `Swapped` makes a pin, which normally serves as RX pin, usable as TX
+/// pin and vice versa. Passing such pins to the constructor enables the SWAP function of the
+/// USART. Either both pins or none have to be swapped.
+///
+/// ```ignore
+/// // PA10 transmits and PA9 receives
+/// let pins = (Swapped(pa10), Swapped(pa9));
+/// let serial = Serial::usart1(dp.USART1, pins, 9600.Bd(), clocks, &mut rcc.apb2);
+/// ```
+pub struct Swapped<PIN>(pub PIN);
+
+impl<PIN> Swapped<PIN> {
+    /// Returns the wrapped pin
+    pub fn into_inner(self) -> PIN {
+        self.0
+    }
 }
 
 // FIXME these should be "closed" traits
 /// TX pin - DO NOT IMPLEMENT THIS TRAIT
-pub unsafe trait TxPin<USART> {}
+pub unsafe trait TxPin<USART> {
+    #[doc(hidden)]
+    const SWAPPED: bool = false;
+}
 
 /// RX pin - DO NOT IMPLEMENT THIS TRAIT
-pub unsafe trait RxPin<USART> {}
+pub unsafe trait RxPin<USART> {
+    #[doc(hidden)]
+    const SWAPPED: bool = false;
+}
+
+unsafe impl<USART, PIN> TxPin<USART> for Swapped<PIN>
+where
+    PIN: RxPin<USART>,
+{
+    const SWAPPED: bool = true;
+}
+
+unsafe impl<USART, PIN> RxPin<USART> for Swapped<PIN>
+where
+    PIN: TxPin<USART>,
+{
+    const SWAPPED: bool = true;
+}
+
+/// RTS pin - DO NOT IMPLEMENT THIS TRAIT
+///
+/// The RTS pin doubles as the driver enable (DE) output in RS-485 mode.
+pub unsafe trait RtsPin<USART> {}
+
+/// CTS pin - DO NOT IMPLEMENT THIS TRAIT
+pub unsafe trait CtsPin<USART> {}
+
+/// Single-wire half-duplex pin - DO NOT IMPLEMENT THIS TRAIT
+///
+/// Implemented for all TX pins in open-drain alternate function mode.
+pub unsafe trait HalfDuplexPin<USART> {}
+
+/// CK pin - DO NOT IMPLEMENT THIS TRAIT
+///
+/// Outputs the clock in synchronous mode.
+pub unsafe trait CkPin<USART> {}
+
+unsafe impl<USART, Gpio, Index> HalfDuplexPin<USART> for Pin<Gpio, Index, AF7<OpenDrain>> where
+    Self: TxPin<USART>
+{
+}
 
 unsafe impl<Otype> TxPin<USART1> for gpioa::PA9<AF7<Otype>> {}
 unsafe impl<Otype> TxPin<USART1> for gpiob::PB6<AF7<Otype>> {}
@@ -66,9 +326,23 @@ unsafe impl<Otype> TxPin<USART3> for gpiob::PB10<AF7<Otype>> {}
 unsafe impl<Otype> TxPin<USART3> for gpioc::PC10<AF7<Otype>> {}
 unsafe impl<Otype> RxPin<USART3> for gpioc::PC11<AF7<Otype>> {}
 
+unsafe impl<Otype> CkPin<USART1> for gpioa::PA8<AF7<Otype>> {}
+unsafe impl<Otype> CkPin<USART2> for gpioa::PA4<AF7<Otype>> {}
+unsafe impl<Otype> CkPin<USART2> for gpiob::PB5<AF7<Otype>> {}
+unsafe impl<Otype> CkPin<USART3> for gpioc::PC12<AF7<Otype>> {}
+
+unsafe impl<Otype> CtsPin<USART1> for gpioa::PA11<AF7<Otype>> {}
+unsafe impl<Otype> RtsPin<USART1> for gpioa::PA12<AF7<Otype>> {}
+
+unsafe impl<Otype> CtsPin<USART2> for gpioa::PA0<AF7<Otype>> {}
+unsafe impl<Otype> RtsPin<USART2> for gpioa::PA1<AF7<Otype>> {}
+
+unsafe impl<Otype> CtsPin<USART3> for gpioa::PA13<AF7<Otype>> {}
+unsafe impl<Otype> RtsPin<USART3> for gpiob::PB14<AF7<Otype>> {}
+
 cfg_if! {
     if #[cfg(any(feature = "gpio-f303", feature = "gpio-f303e", feature = "gpio-f373"))] {
-        use crate::gpio::{gpiod, gpioe};
+        use crate::gpio::{gpiod, gpioe, gpiof};
 
         unsafe impl<Otype> TxPin<USART1> for gpioe::PE0<AF7<Otype>> {}
         unsafe impl<Otype> RxPin<USART1> for gpioe::PE1<AF7<Otype>> {}
@@ -79,6 +353,16 @@ cfg_if! {
         unsafe impl<Otype> TxPin<USART3> for gpiod::PD8<AF7<Otype>> {}
         unsafe impl<Otype> RxPin<USART3> for gpiod::PD9<AF7<Otype>> {}
         unsafe impl<Otype> RxPin<USART3> for gpioe::PE15<AF7<Otype>> {}
+
+        unsafe impl<Otype> CkPin<USART2> for gpiod::PD7<AF7<Otype>> {}
+        unsafe impl<Otype> CkPin<USART3> for gpiod::PD10<AF7<Otype>> {}
+
+        unsafe impl<Otype> CtsPin<USART2> for gpiod::PD3<AF7<Otype>> {}
+        unsafe impl<Otype> RtsPin<USART2> for gpiod::PD4<AF7<Otype>> {}
+
+        unsafe impl<Otype> CtsPin<USART3> for gpiod::PD11<AF7<Otype>> {}
+        unsafe impl<Otype> RtsPin<USART3> for gpiod::PD12<AF7<Otype>> {}
+        unsafe impl<Otype> RtsPin<USART3> for gpiof::PF6<AF7<Otype>> {}
     }
 }
 
@@ -88,6 +372,9 @@ cfg_if! {
         unsafe impl<Otype> RxPin<USART2> for gpioa::PA15<AF7<Otype>> {}
 
         unsafe impl<Otype> RxPin<USART3> for gpiob::PB11<AF7<Otype>> {}
+
+        unsafe impl<Otype> CkPin<USART3> for gpiob::PB12<AF7<Otype>> {}
+        unsafe impl<Otype> CtsPin<USART3> for gpiob::PB13<AF7<Otype>> {}
     }
 }
 
@@ -107,11 +394,320 @@ pub struct Tx<USART> {
     _usart: PhantomData<USART>,
 }
 
+/// USART instance -- DO NOT IMPLEMENT THIS TRAIT
+pub unsafe trait Instance: Deref<Target = RegisterBlock> {
+    #[doc(hidden)]
+    type APB;
+    #[doc(hidden)]
+    fn enable_clock(apb: &mut Self::APB);
+    #[doc(hidden)]
+    fn clock(clocks: &Clocks) -> Hertz;
+}
+
+/// Returns whether the TX and RX pins are swapped
+///
+/// # Panics
+///
+/// Panics if only one of the pins is swapped, as the USART can only swap both.
+fn swapped<USART, TX, RX>() -> bool
+where
+    TX: TxPin<USART>,
+    RX: RxPin<USART>,
+{
+    crate::assert!(
+        TX::SWAPPED == RX::SWAPPED,
+        "either both or no pins are swapped"
+    );
+    TX::SWAPPED
+}
+
+/// Enables and resets the USART and configures its baud rate
+fn init<USART>(usart: &USART, baud_rate: Baud, clocks: Clocks, apb: &mut USART::APB)
+where
+    USART: Instance,
+{
+    USART::enable_clock(apb);
+
+    let brr = USART::clock(&clocks).0 / baud_rate.integer();
+    crate::assert!(brr >= 16, "impossible baud rate");
+    // NOTE(write): uses all bits of this register.
+    usart.brr.write(|w| unsafe { w.bits(brr) });
+}
+
+/// Unmasks the interrupt of an EXTI line, which is connected to an internal wakeup event
+fn unmask_wakeup_line(exti: &mut EXTI, line: u8) {
+    #[cfg(feature = "svd-f373")]
+    let imr = &exti.imr;
+    #[cfg(not(feature = "svd-f373"))]
+    let imr = &exti.imr1;
+
+    // NOTE(unsafe) only the bit of this line is changed
+    imr.modify(|r, w| unsafe { w.bits(r.bits() | 1 << line) });
+}
+
+/// Masks the interrupt of an EXTI line, which is connected to an internal wakeup event
+fn mask_wakeup_line(exti: &mut EXTI, line: u8) {
+    #[cfg(feature = "svd-f373")]
+    let imr = &exti.imr;
+    #[cfg(not(feature = "svd-f373"))]
+    let imr = &exti.imr1;
+
+    // NOTE(unsafe) only the bit of this line is changed
+    imr.modify(|r, w| unsafe { w.bits(r.bits() & !(1 << line)) });
+}
+
+/// Serial abstraction for single-wire half-duplex communication
+///
+/// Both transmission and reception happen on the TX pin, which has to be configured as
+/// open-drain output with an external or internal pull-up. The receiver is turned off while
+/// transmitting, so the transmitted data is not read back.
+pub struct HalfDuplex<USART, PIN> {
+    usart: USART,
+    pin: PIN,
+}
+
+impl<USART, PIN> HalfDuplex<USART, PIN>
+where
+    USART: Instance,
+{
+    /// Configures a USART peripheral for single-wire half-duplex communication
+    pub fn new(
+        usart: USART,
+        pin: PIN,
+        baud_rate: Baud,
+        clocks: Clocks,
+        apb: &mut USART::APB,
+    ) -> Self
+    where
+        PIN: HalfDuplexPin<USART>,
+    {
+        init(&usart, baud_rate, clocks, apb);
+
+        // HDSEL can only be written while the USART is disabled, so before setting UE
+        usart.cr3.modify(|_, w| w.hdsel().set_bit());
+        usart.cr1.modify(|_, w| {
+            w.ue().enabled();
+            w.re().enabled();
+            w.te().enabled()
+        });
+
+        HalfDuplex { usart, pin }
+    }
+
+    /// Releases the USART peripheral and associated pin
+    pub fn free(self) -> (USART, PIN) {
+        (self.usart, self.pin)
+    }
+}
+
+impl<USART, PIN> serial::Read<u8> for HalfDuplex<USART, PIN>
+where
+    USART: Instance,
+{
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u8, Error> {
+        if self.usart.cr1.read().re().bit_is_clear() {
+            // Turn the receiver back on once the last transmission is complete
+            if self.usart.isr.read().tc().bit_is_clear() {
+                return Err(nb::Error::WouldBlock);
+            }
+            self.usart.cr1.modify(|_, w| w.re().enabled());
+        }
+
+        Err(if let Some(error) = take_error(&self.usart) {
+            nb::Error::Other(error)
+        } else if self.usart.isr.read().rxne().bit_is_set() {
+            // NOTE(read_volatile) see `Tx::write`
+            return Ok(unsafe { ptr::read_volatile(&self.usart.rdr as *const _ as *const _) });
+        } else {
+            nb::Error::WouldBlock
+        })
+    }
+}
+
+impl<USART, PIN> serial::Write<u8> for HalfDuplex<USART, PIN>
+where
+    USART: Instance,
+{
+    type Error = Infallible;
+
+    fn flush(&mut self) -> nb::Result<(), Infallible> {
+        if self.usart.isr.read().tc().bit_is_set() {
+            self.usart.cr1.modify(|_, w| w.re().enabled());
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    fn write(&mut self, byte: u8) -> nb::Result<(), Infallible> {
+        if self.usart.isr.read().txe().bit_is_set() {
+            self.usart.cr1.modify(|_, w| w.re().disabled());
+            // NOTE(unsafe) atomic write to stateless register
+            // NOTE(write_volatile) 8-bit write that's not possible through the svd2rust API
+            unsafe { ptr::write_volatile(&self.usart.tdr as *const _ as *mut _, byte) }
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+impl<USART, PIN> blocking::serial::write::Default<u8> for HalfDuplex<USART, PIN> where
+    USART: Instance
+{
+}
+
+/// Serial abstraction for synchronous master mode
+///
+/// The USART outputs a clock on the CK pin and is used like a SPI master with MOSI on the TX
+/// pin and MISO on the RX pin. The data is transmitted most significant bit first by
+/// default. There is no chip select, this has to be handled by a GPIO pin.
+pub struct Synchronous<USART, PINS> {
+    usart: USART,
+    pins: PINS,
+}
+
+impl<USART, TX, RX, CK> Synchronous<USART, (TX, RX, CK)>
+where
+    USART: Instance,
+{
+    /// Configures a USART peripheral for synchronous master mode
+    ///
+    /// The clock frequency equals `baud_rate`. A clock pulse is also generated for the last
+    /// data bit, which SPI devices usually need, see
+    /// [`set_last_bit_clock_pulse`](Synchronous::set_last_bit_clock_pulse).
+    pub fn new(
+        usart: USART,
+        pins: (TX, RX, CK),
+        mode: Mode,
+        baud_rate: Baud,
+        clocks: Clocks,
+        apb: &mut USART::APB,
+    ) -> Self
+    where
+        TX: TxPin<USART>,
+        RX: RxPin<USART>,
+        CK: CkPin<USART>,
+    {
+        init(&usart, baud_rate, clocks, apb);
+
+        let swap = swapped::<USART, TX, RX>();
+        // CR2 can only be written while the USART is disabled, so before setting UE
+        usart.cr2.modify(|_, w| {
+            w.swap().bit(swap);
+            w.msbfirst().set_bit();
+            match mode.polarity {
+                Polarity::IdleLow => w.cpol().clear_bit(),
+                Polarity::IdleHigh => w.cpol().set_bit(),
+            };
+            match mode.phase {
+                Phase::CaptureOnFirstTransition => w.cpha().clear_bit(),
+                Phase::CaptureOnSecondTransition => w.cpha().set_bit(),
+            };
+            w.lbcl().set_bit();
+            w.clken().set_bit()
+        });
+        usart.cr1.modify(|_, w| {
+            w.ue().enabled();
+            w.re().enabled();
+            w.te().enabled()
+        });
+
+        Synchronous { usart, pins }
+    }
+}
+
+impl<USART, PINS> Synchronous<USART, PINS>
+where
+    USART: Instance,
+{
+    /// Enables or disables the clock pulse of the last data bit
+    pub fn set_last_bit_clock_pulse(&mut self, enable: bool) {
+        // LBCL can only be written while the USART is disabled
+        self.usart.cr1.modify(|_, w| w.ue().disabled());
+        self.usart.cr2.modify(|_, w| w.lbcl().bit(enable));
+        self.usart.cr1.modify(|_, w| w.ue().enabled());
+    }
+
+    /// Sets the order in which the data bits are transmitted and received
+    pub fn set_bit_order(&mut self, order: BitOrder) {
+        // MSBFIRST can only be written while the USART is disabled
+        self.usart.cr1.modify(|_, w| w.ue().disabled());
+        self.usart
+            .cr2
+            .modify(|_, w| w.msbfirst().bit(order == BitOrder::MsbFirst));
+        self.usart.cr1.modify(|_, w| w.ue().enabled());
+    }
+
+    /// Releases the USART peripheral and associated pins
+    pub fn free(self) -> (USART, PINS) {
+        self.usart.cr1.modify(|_, w| w.ue().disabled());
+        self.usart.cr2.modify(|_, w| w.clken().clear_bit());
+
+        (self.usart, self.pins)
+    }
+}
+
+impl<USART, PINS> FullDuplex<u8> for Synchronous<USART, PINS>
+where
+    USART: Instance,
+{
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u8, Error> {
+        Err(if let Some(error) = take_error(&self.usart) {
+            nb::Error::Other(error)
+        } else if self.usart.isr.read().rxne().bit_is_set() {
+            // NOTE(read_volatile) see `Tx::write`
+            return Ok(unsafe { ptr::read_volatile(&self.usart.rdr as *const _ as *const _) });
+        } else {
+            nb::Error::WouldBlock
+        })
+    }
+
+    fn send(&mut self, byte: u8) -> nb::Result<(), Error> {
+        if self.usart.isr.read().txe().bit_is_set() {
+            // NOTE(unsafe) atomic write to stateless register
+            // NOTE(write_volatile) 8-bit write that's not possible through the svd2rust API
+            unsafe { ptr::write_volatile(&self.usart.tdr as *const _ as *mut _, byte) }
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+impl<USART, PINS> blocking::spi::transfer::Default<u8> for Synchronous<USART, PINS> where
+    USART: Instance
+{
+}
+impl<USART, PINS> blocking::spi::write::Default<u8> for Synchronous<USART, PINS> where
+    USART: Instance
+{
+}
+
 macro_rules! hal {
     ($(
         $USARTX:ident: ($usartX:ident, $APB:ident, $usartXen:ident, $usartXrst:ident, $pclkX:ident),
     )+) => {
         $(
+            unsafe impl Instance for $USARTX {
+                type APB = $APB;
+
+                fn enable_clock(apb: &mut $APB) {
+                    // enable or reset $USARTX
+                    apb.enr().modify(|_, w| w.$usartXen().set_bit());
+                    apb.rstr().modify(|_, w| w.$usartXrst().set_bit());
+                    apb.rstr().modify(|_, w| w.$usartXrst().clear_bit());
+                }
+
+                fn clock(clocks: &Clocks) -> Hertz {
+                    clocks.$pclkX()
+                }
+            }
+
             impl<TX, RX> Serial<$USARTX, (TX, RX)> {
                 /// Configures a USART peripheral to provide serial communication
                 pub fn $usartX(
@@ -125,15 +721,11 @@ macro_rules! hal {
                     TX: TxPin<$USARTX>,
                     RX: RxPin<$USARTX>,
                 {
-                    // enable or reset $USARTX
-                    apb.enr().modify(|_, w| w.$usartXen().set_bit());
-                    apb.rstr().modify(|_, w| w.$usartXrst().set_bit());
-                    apb.rstr().modify(|_, w| w.$usartXrst().clear_bit());
+                    init(&usart, baud_rate, clocks, apb);
 
-                    let brr = clocks.$pclkX().0 / baud_rate.integer();
-                    crate::assert!(brr >= 16, "impossible baud rate");
-                    // NOTE(write): uses all bits of this register.
-                    usart.brr.write(|w| unsafe { w.bits(brr) });
+                    let swap = swapped::<$USARTX, TX, RX>();
+                    // SWAP can only be written while the USART is disabled, so before setting UE
+                    usart.cr2.modify(|_, w| w.swap().bit(swap));
 
                     usart.cr1.modify(|_, w| {
                         w.ue().enabled();  // enable USART
@@ -144,6 +736,117 @@ macro_rules! hal {
                     Serial { usart, pins }
                 }
 
+                /// Enables RTS/CTS hardware flow control
+                ///
+                /// The receiver deasserts RTS while its data register is full and the transmitter
+                /// holds back the next character until CTS is asserted.
+                pub fn with_flow_control<RTS, CTS>(
+                    self,
+                    rts: RTS,
+                    cts: CTS,
+                ) -> Serial<$USARTX, (TX, RX, RTS, CTS)>
+                where
+                    RTS: RtsPin<$USARTX>,
+                    CTS: CtsPin<$USARTX>,
+                {
+                    let (tx, rx) = self.pins;
+
+                    // CTSE and RTSE can only be written while the USART is disabled
+                    self.usart.cr1.modify(|_, w| w.ue().disabled());
+                    self.usart.cr3.modify(|_, w| {
+                        w.rtse().set_bit();
+                        w.ctse().set_bit()
+                    });
+                    self.usart.cr1.modify(|_, w| w.ue().enabled());
+
+                    Serial {
+                        usart: self.usart,
+                        pins: (tx, rx, rts, cts),
+                    }
+                }
+            }
+
+            impl<TX, RX> Serial<$USARTX, (TX, RX)> {
+                /// Enables RS-485 mode, in which the hardware drives the transceiver's driver
+                /// enable input through the `de` pin
+                ///
+                /// DE is asserted for the duration of every transmission, including the
+                /// configured assertion and deassertion times.
+                pub fn with_rs485<DE>(
+                    self,
+                    de: DE,
+                    config: Rs485Config,
+                ) -> Serial<$USARTX, (TX, RX, DE)>
+                where
+                    DE: RtsPin<$USARTX>,
+                {
+                    let (tx, rx) = self.pins;
+
+                    // DEM, DEP, DEAT and DEDT can only be written while the USART is disabled
+                    self.usart.cr1.modify(|_, w| w.ue().disabled());
+                    self.usart.cr3.modify(|_, w| {
+                        match config.polarity {
+                            DePolarity::ActiveHigh => w.dep().clear_bit(),
+                            DePolarity::ActiveLow => w.dep().set_bit(),
+                        };
+                        w.dem().set_bit()
+                    });
+                    // NOTE(unsafe) the times have been checked to fit into 5 bits
+                    self.usart.cr1.modify(|_, w| unsafe {
+                        w.deat().bits(config.assertion_samples);
+                        w.dedt().bits(config.deassertion_samples)
+                    });
+                    self.usart.cr1.modify(|_, w| w.ue().enabled());
+
+                    Serial {
+                        usart: self.usart,
+                        pins: (tx, rx, de),
+                    }
+                }
+            }
+
+            impl<TX, RX, DE> Serial<$USARTX, (TX, RX, DE)> {
+                /// Disables RS-485 mode and releases the DE pin
+                pub fn without_rs485(self) -> (Serial<$USARTX, (TX, RX)>, DE) {
+                    let (tx, rx, de) = self.pins;
+
+                    self.usart.cr1.modify(|_, w| w.ue().disabled());
+                    self.usart.cr3.modify(|_, w| w.dem().clear_bit());
+                    self.usart.cr1.modify(|_, w| w.ue().enabled());
+
+                    (
+                        Serial {
+                            usart: self.usart,
+                            pins: (tx, rx),
+                        },
+                        de,
+                    )
+                }
+            }
+
+            impl<TX, RX, RTS, CTS> Serial<$USARTX, (TX, RX, RTS, CTS)> {
+                /// Disables RTS/CTS hardware flow control and releases the RTS and CTS pins
+                pub fn without_flow_control(self) -> (Serial<$USARTX, (TX, RX)>, (RTS, CTS)) {
+                    let (tx, rx, rts, cts) = self.pins;
+
+                    self.usart.cr1.modify(|_, w| w.ue().disabled());
+                    self.usart.cr3.modify(|_, w| {
+                        w.rtse().clear_bit();
+                        w.ctse().clear_bit()
+                    });
+                    self.usart.cr1.modify(|_, w| w.ue().enabled());
+
+                    (
+                        Serial {
+                            usart: self.usart,
+                            pins: (tx, rx),
+                        },
+                        (rts, cts),
+                    )
+                }
+            }
+
+            impl<PINS> Serial<$USARTX, PINS> {
                 /// Starts listening for an interrupt event
                 pub fn listen(&mut self, event: Event) {
                     match event {
@@ -156,6 +859,21 @@ macro_rules! hal {
                         Event::Tc => {
                             self.usart.cr1.modify(|_, w| w.tcie().set_bit())
                         },
+                        Event::Idle => {
+                            self.usart.cr1.modify(|_, w| w.idleie().set_bit())
+                        },
+                        Event::ReceiverTimeout => {
+                            self.usart.cr1.modify(|_, w| w.rtoie().set_bit())
+                        },
+                        Event::CharacterMatch => {
+                            self.usart.cr1.modify(|_, w| w.cmie().set_bit())
+                        },
+                        Event::Error => {
+                            self.usart.cr3.modify(|_, w| w.eie().set_bit())
+                        },
+                        Event::ParityError => {
+                            self.usart.cr1.modify(|_, w| w.peie().set_bit())
+                        },
                     }
                 }
 
@@ -171,6 +889,21 @@ macro_rules! hal {
                         Event::Tc => {
                             self.usart.cr1.modify(|_, w| w.tcie().clear_bit())
                         },
+                        Event::Idle => {
+                            self.usart.cr1.modify(|_, w| w.idleie().clear_bit())
+                        },
+                        Event::ReceiverTimeout => {
+                            self.usart.cr1.modify(|_, w| w.rtoie().clear_bit())
+                        },
+                        Event::CharacterMatch => {
+                            self.usart.cr1.modify(|_, w| w.cmie().clear_bit())
+                        },
+                        Event::Error => {
+                            self.usart.cr3.modify(|_, w| w.eie().clear_bit())
+                        },
+                        Event::ParityError => {
+                            self.usart.cr1.modify(|_, w| w.peie().clear_bit())
+                        },
                     }
                 }
 
@@ -192,6 +925,264 @@ macro_rules! hal {
                     isr.rxne().bit_is_set()
                 }
 
+                /// Return true if the receive line went idle after receiving data
+                pub fn is_idle(&self) -> bool {
+                    let isr = unsafe { (*$USARTX::ptr()).isr.read() };
+                    isr.idle().bit_is_set()
+                }
+
+                /// Clear the idle line flag
+                ///
+                /// The flag is only set again after another character has been received.
+                pub fn clear_idle(&mut self) {
+                    // NOTE(unsafe, write) atomic write to a stateless register
+                    unsafe { (*$USARTX::ptr()).icr.write(|w| w.idlecf().clear()) };
+                }
+
+                /// Clear all reception error flags (parity, framing, noise and overrun)
+                pub fn clear_errors(&mut self) {
+                    clear_errors(&self.usart);
+                }
+
+                /// Enables or disables overrun detection
+                ///
+                /// With overrun detection disabled, a received character which could not be
+                /// read in time simply overwrites the previous one, without stalling the
+                /// receiver or reporting [`Error::Overrun`].
+                pub fn set_overrun_detection(&mut self, enable: bool) {
+                    // OVRDIS can only be written while the USART is disabled
+                    self.usart.cr1.modify(|_, w| w.ue().disabled());
+                    self.usart.cr3.modify(|_, w| w.ovrdis().bit(!enable));
+                    self.usart.cr1.modify(|_, w| w.ue().enabled());
+                }
+
+                /// Inverts the RX pin, so that idle is low and the start bit is high
+                pub fn set_rx_inversion(&mut self, invert: bool) {
+                    // RXINV can only be written while the USART is disabled
+                    self.usart.cr1.modify(|_, w| w.ue().disabled());
+                    self.usart.cr2.modify(|_, w| w.rxinv().bit(invert));
+                    self.usart.cr1.modify(|_, w| w.ue().enabled());
+                }
+
+                /// Inverts the TX pin, so that idle is low and the start bit is high
+                pub fn set_tx_inversion(&mut self, invert: bool) {
+                    // TXINV can only be written while the USART is disabled
+                    self.usart.cr1.modify(|_, w| w.ue().disabled());
+                    self.usart.cr2.modify(|_, w| w.txinv().bit(invert));
+                    self.usart.cr1.modify(|_, w| w.ue().enabled());
+                }
+
+                /// Inverts the data bits (including parity), but not the start and stop bits
+                pub fn set_data_inversion(&mut self, invert: bool) {
+                    // DATAINV can only be written while the USART is disabled
+                    self.usart.cr1.modify(|_, w| w.ue().disabled());
+                    self.usart.cr2.modify(|_, w| w.datainv().bit(invert));
+                    self.usart.cr1.modify(|_, w| w.ue().enabled());
+                }
+
+                /// Configures the order in which the data bits are transmitted and received
+                pub fn set_bit_order(&mut self, order: BitOrder) {
+                    // MSBFIRST can only be written while the USART is disabled
+                    self.usart.cr1.modify(|_, w| w.ue().disabled());
+                    self.usart.cr2.modify(|_, w| w.msbfirst().bit(order == BitOrder::MsbFirst));
+                    self.usart.cr1.modify(|_, w| w.ue().enabled());
+                }
+
+                /// Starts automatic baud rate detection on the next received character
+                ///
+                /// Poll [`auto_baud_rate`](Self::auto_baud_rate) to get the result. Once
+                /// detected, the baud rate is kept and the received character can be read
+                /// as usual.
+                pub fn start_auto_baud(&mut self, mode: AutoBaudMode) {
+                    let abrmod = match mode {
+                        AutoBaudMode::StartBit => 0b00,
+                        AutoBaudMode::FallingEdge => 0b01,
+                        AutoBaudMode::Frame0x7F => 0b10,
+                        AutoBaudMode::Frame0x55 => 0b11,
+                    };
+
+                    // ABREN and ABRMOD can only be written while the USART is disabled,
+                    // which also restarts an already finished detection
+                    self.usart.cr1.modify(|_, w| w.ue().disabled());
+                    // NOTE(unsafe) all 2 bit values are valid modes
+                    self.usart.cr2.modify(|_, w| unsafe {
+                        w.abrmod().bits(abrmod);
+                        w.abren().set_bit()
+                    });
+                    self.usart.cr1.modify(|_, w| w.ue().enabled());
+                }
+
+                /// Returns the detected baud rate, once automatic baud rate detection
+                /// is complete
+                ///
+                /// Returns [`Error::AutoBaudRate`] if the received character did not allow
+                /// to measure the baud rate, e.g. because it was too fast or too slow.
+                pub fn auto_baud_rate(&mut self, clocks: Clocks) -> nb::Result<Baud, Error> {
+                    let isr = self.usart.isr.read();
+
+                    if isr.abre().bit_is_set() {
+                        // Another detection is only started by a new request
+                        self.usart.rqr.write(|w| w.abrrq().set_bit());
+                        Err(nb::Error::Other(Error::AutoBaudRate))
+                    } else if isr.abrf().bit_is_set() {
+                        let brr = self.usart.brr.read().bits();
+                        Ok(Baud(<$USARTX as Instance>::clock(&clocks).0 / brr))
+                    } else {
+                        Err(nb::Error::WouldBlock)
+                    }
+                }
+
+                /// Configures the receiver timeout, in bit times
+                ///
+                /// The timeout starts counting at the end of each received character. Passing
+                /// `None` disables the receiver timeout. For Modbus RTU, a timeout of 35 bit
+                /// times (3.5 characters of 10 bits) marks the end of a frame.
+                ///
+                /// # Panics
+                ///
+                /// Panics if `bit_times` does not fit into 24 bits.
+                pub fn set_receiver_timeout(&mut self, bit_times: Option<u32>) {
+                    match bit_times {
+                        Some(bit_times) => {
+                            crate::assert!(bit_times <= 0x00FF_FFFF);
+                            // NOTE(unsafe) the value has been checked to fit into 24 bits
+                            self.usart.rtor.modify(|_, w| unsafe { w.rto().bits(bit_times) });
+                            self.usart.cr2.modify(|_, w| w.rtoen().set_bit());
+                        }
+                        None => self.usart.cr2.modify(|_, w| w.rtoen().clear_bit()),
+                    }
+                }
+
+                /// Return true if the receiver timeout elapsed
+                pub fn is_receiver_timeout(&self) -> bool {
+                    let isr = unsafe { (*$USARTX::ptr()).isr.read() };
+                    isr.rtof().bit_is_set()
+                }
+
+                /// Clear the receiver timeout flag
+                pub fn clear_receiver_timeout(&mut self) {
+                    // NOTE(unsafe, write) atomic write to a stateless register
+                    unsafe { (*$USARTX::ptr()).icr.write(|w| w.rtocf().clear()) };
+                }
+
+                /// Configures the character which sets the character match flag when received
+                ///
+                /// The whole received byte is compared, e.g. `b'\n'` can be used to detect the
+                /// end of a line of text.
+                pub fn set_match_character(&mut self, character: u8) {
+                    // ADD can only be written while the USART is disabled
+                    self.usart.cr1.modify(|_, w| w.ue().disabled());
+                    // NOTE(unsafe) each half of the address field is 4 bits wide
+                    self.usart.cr2.modify(|_, w| unsafe {
+                        w.add4().bits(character >> 4);
+                        w.add0().bits(character & 0x0F)
+                    });
+                    self.usart.cr1.modify(|_, w| w.ue().enabled());
+                }
+
+                /// Return true if the match character has been received
+                pub fn is_character_match(&self) -> bool {
+                    let isr = unsafe { (*$USARTX::ptr()).isr.read() };
+                    isr.cmf().bit_is_set()
+                }
+
+                /// Clear the character match flag
+                pub fn clear_character_match(&mut self) {
+                    // NOTE(unsafe, write) atomic write to a stateless register
+                    unsafe { (*$USARTX::ptr()).icr.write(|w| w.cmcf().clear()) };
+                }
+
+                /// Sets the number of data bits of a character
+                ///
+                /// 9-bit characters have to be read and written with
+                /// [`read_word`](Self::read_word) and [`write_word`](Self::write_word), the
+                /// `u8` interfaces drop the 9th bit.
+                pub fn set_word_length(&mut self, length: WordLength) {
+                    // M0 and M1 can only be written while the USART is disabled
+                    self.usart.cr1.modify(|_, w| w.ue().disabled());
+                    self.usart.cr1.modify(|_, w| {
+                        match length {
+                            WordLength::DataBits7 => w.m1().set_bit().m0().clear_bit(),
+                            WordLength::DataBits8 => w.m1().clear_bit().m0().clear_bit(),
+                            WordLength::DataBits9 => w.m1().clear_bit().m0().set_bit(),
+                        };
+                        w.ue().enabled()
+                    });
+                }
+
+                /// Reads a character of up to 9 bits
+                pub fn read_word(&mut self) -> nb::Result<u16, Error> {
+                    let mut rx: Rx<$USARTX> = Rx {
+                        _usart: PhantomData,
+                    };
+                    rx.read_word()
+                }
+
+                /// Writes a character of up to 9 bits
+                pub fn write_word(&mut self, word: u16) -> nb::Result<(), Infallible> {
+                    let mut tx: Tx<$USARTX> = Tx {
+                        _usart: PhantomData,
+                    };
+                    tx.write_word(word)
+                }
+
+                /// Enables mute mode with the given wakeup condition
+                ///
+                /// While muted, the receiver discards all characters and sets no flags, so no
+                /// interrupts are triggered. Mute mode is entered with
+                /// [`enter_mute`](Self::enter_mute) and, with address wakeup, when an address
+                /// character with a different address is received.
+                ///
+                /// # Panics
+                ///
+                /// Panics if the address does not fit into the address length.
+                pub fn enable_mute_mode(&mut self, wakeup: Wakeup) {
+                    // WAKE, ADDM7 and ADD can only be written while the USART is disabled
+                    self.usart.cr1.modify(|_, w| w.ue().disabled());
+                    match wakeup {
+                        Wakeup::IdleLine => {
+                            self.usart.cr1.modify(|_, w| w.wake().clear_bit());
+                        }
+                        Wakeup::Address4(address) => {
+                            crate::assert!(address < 0x10);
+                            // NOTE(unsafe) the address fits into 4 bits
+                            self.usart.cr2.modify(|_, w| unsafe {
+                                w.addm7().clear_bit();
+                                w.add0().bits(address)
+                            });
+                            self.usart.cr1.modify(|_, w| w.wake().set_bit());
+                        }
+                        Wakeup::Address7(address) => {
+                            crate::assert!(address < 0x80);
+                            // NOTE(unsafe) each half of the address field is 4 bits wide
+                            self.usart.cr2.modify(|_, w| unsafe {
+                                w.addm7().set_bit();
+                                w.add4().bits(address >> 4);
+                                w.add0().bits(address & 0x0F)
+                            });
+                            self.usart.cr1.modify(|_, w| w.wake().set_bit());
+                        }
+                    }
+                    self.usart.cr1.modify(|_, w| {
+                        w.mme().set_bit();
+                        w.ue().enabled()
+                    });
+                }
+
+                /// Disables mute mode, the receiver is never muted anymore
+                pub fn disable_mute_mode(&mut self) {
+                    self.usart.cr1.modify(|_, w| w.mme().clear_bit());
+                }
+
+                /// Mutes the receiver until the wakeup condition occurs
+                pub fn enter_mute(&mut self) {
+                    self.usart.rqr.write(|w| w.mmrq().set_bit());
+                }
+
+                /// Return true if the receiver is muted
+                pub fn is_muted(&self) -> bool {
+                    self.usart.isr.read().rwu().bit_is_set()
+                }
 
                 /// Splits the `Serial` abstraction into a transmitter and a receiver half
                 pub fn split(self) -> (Tx<$USARTX>, Rx<$USARTX>) {
@@ -206,12 +1197,12 @@ macro_rules! hal {
                 }
 
                 /// Releases the USART peripheral and associated pins
-                pub fn free(self) -> ($USARTX, (TX, RX)) {
+                pub fn free(self) -> ($USARTX, PINS) {
                     (self.usart, self.pins)
                 }
             }
 
-            impl<TX, RX> serial::Read<u8> for Serial<$USARTX, (TX, RX)> {
+            impl<PINS> serial::Read<u8> for Serial<$USARTX, PINS> {
                 type Error = Error;
 
                 fn read(&mut self) -> nb::Result<u8, Error> {
@@ -222,7 +1213,7 @@ macro_rules! hal {
                 }
             }
 
-            impl<TX, RX> serial::Write<u8> for Serial<$USARTX, (TX, RX)> {
+            impl<PINS> serial::Write<u8> for Serial<$USARTX, PINS> {
                 type Error = Infallible;
 
                 fn flush(&mut self) -> nb::Result<(), Infallible> {
@@ -240,29 +1231,79 @@ macro_rules! hal {
                 }
             }
 
+            impl Rx<$USARTX> {
+                /// Reads a character of up to 9 bits, see [`WordLength`]
+                pub fn read_word(&mut self) -> nb::Result<u16, Error> {
+                    // NOTE(unsafe) only reads and stateless writes are performed on the
+                    // register block
+                    let usart = unsafe { &*$USARTX::ptr() };
+
+                    Err(if let Some(error) = take_error(usart) {
+                        nb::Error::Other(error)
+                    } else if usart.isr.read().rxne().bit_is_set() {
+                        return Ok(usart.rdr.read().rdr().bits());
+                    } else {
+                        nb::Error::WouldBlock
+                    })
+                }
+
+                /// Return true if the receive line went idle after receiving data
+                pub fn is_idle(&self) -> bool {
+                    // NOTE(unsafe) atomic read with no side effects
+                    let isr = unsafe { (*$USARTX::ptr()).isr.read() };
+                    isr.idle().bit_is_set()
+                }
+
+                /// Clear the idle line flag
+                pub fn clear_idle(&mut self) {
+                    // NOTE(unsafe, write) atomic write to a stateless register
+                    unsafe { (*$USARTX::ptr()).icr.write(|w| w.idlecf().clear()) };
+                }
+
+                /// Clear all reception error flags (parity, framing, noise and overrun)
+                pub fn clear_errors(&mut self) {
+                    // NOTE(unsafe) only the stateless ICR register is written
+                    clear_errors(unsafe { &*$USARTX::ptr() });
+                }
+
+                /// Return true if the receiver timeout elapsed
+                pub fn is_receiver_timeout(&self) -> bool {
+                    // NOTE(unsafe) atomic read with no side effects
+                    let isr = unsafe { (*$USARTX::ptr()).isr.read() };
+                    isr.rtof().bit_is_set()
+                }
+
+                /// Clear the receiver timeout flag
+                pub fn clear_receiver_timeout(&mut self) {
+                    // NOTE(unsafe, write) atomic write to a stateless register
+                    unsafe { (*$USARTX::ptr()).icr.write(|w| w.rtocf().clear()) };
+                }
+
+                /// Return true if the match character has been received
+                pub fn is_character_match(&self) -> bool {
+                    // NOTE(unsafe) atomic read with no side effects
+                    let isr = unsafe { (*$USARTX::ptr()).isr.read() };
+                    isr.cmf().bit_is_set()
+                }
+
+                /// Clear the character match flag
+                pub fn clear_character_match(&mut self) {
+                    // NOTE(unsafe, write) atomic write to a stateless register
+                    unsafe { (*$USARTX::ptr()).icr.write(|w| w.cmcf().clear()) };
+                }
+            }
+
             impl serial::Read<u8> for Rx<$USARTX> {
                 type Error = Error;
 
                 fn read(&mut self) -> nb::Result<u8, Error> {
-                    // NOTE(unsafe) atomic read with no side effects
-                    let isr = unsafe { (*$USARTX::ptr()).isr.read() };
+                    // NOTE(unsafe) only reads and stateless writes are performed on the
+                    // register block
+                    let usart = unsafe { &*$USARTX::ptr() };
 
-                    // NOTE(unsafe, write) write accessor for atomic writes with no side effects
-                    let icr = unsafe { &(*$USARTX::ptr()).icr };
-
-                    Err(if isr.pe().bit_is_set() {
-                        icr.write(|w| w.pecf().clear());
-                        nb::Error::Other(Error::Parity)
-                    } else if isr.fe().bit_is_set() {
-                        icr.write(|w| w.fecf().clear());
-                        nb::Error::Other(Error::Framing)
-                    } else if isr.nf().bit_is_set() {
-                        icr.write(|w| w.ncf().clear());
-                        nb::Error::Other(Error::Noise)
-                    } else if isr.ore().bit_is_set() {
-                        icr.write(|w| w.orecf().clear());
-                        nb::Error::Other(Error::Overrun)
-                    } else if isr.rxne().bit_is_set() {
+                    Err(if let Some(error) = take_error(usart) {
+                        nb::Error::Other(error)
+                    } else if usart.isr.read().rxne().bit_is_set() {
                         // NOTE(read_volatile) see `write_volatile` below
                         return Ok(unsafe {
                             ptr::read_volatile(&(*$USARTX::ptr()).rdr as *const _ as *const _)
@@ -275,9 +1316,9 @@ macro_rules! hal {
 
             impl serial::Write<u8> for Tx<$USARTX> {
                 // NOTE(Infallible) See section "29.7 USART interrupts"; the only possible errors during
-                // transmission are: clear to send (which is disabled in this case) errors and
-                // framing errors (which only occur in SmartCard mode); neither of these apply to
-                // our hardware configuration
+                // transmission are: clear to send errors (with hardware flow control the transmitter
+                // simply stalls until CTS is asserted) and framing errors (which only occur in
+                // SmartCard mode); neither of these apply to our hardware configuration
                 type Error = Infallible;
 
                 fn flush(&mut self) -> nb::Result<(), Infallible> {
@@ -310,6 +1351,23 @@ macro_rules! hal {
 
             impl blocking::serial::write::Default<u8> for Tx<$USARTX> {}
 
+            impl Tx<$USARTX> {
+                /// Writes a character of up to 9 bits, see [`WordLength`]
+                pub fn write_word(&mut self, word: u16) -> nb::Result<(), Infallible> {
+                    // NOTE(unsafe) atomic read with no side effects
+                    let isr = unsafe { (*$USARTX::ptr()).isr.read() };
+
+                    if isr.txe().bit_is_set() {
+                        // NOTE(unsafe) atomic write to stateless register, the character is
+                        // truncated to the 9 bits of TDR
+                        unsafe { (*$USARTX::ptr()).tdr.write(|w| w.tdr().bits(word & 0x1FF)) };
+                        Ok(())
+                    } else {
+                        Err(nb::Error::WouldBlock)
+                    }
+                }
+            }
+
             #[cfg(any(feature = "stm32f302", feature = "stm32f303"))]
             impl Rx<$USARTX> {
                 /// Fill the buffer with received data using DMA.
@@ -330,6 +1388,128 @@ macro_rules! hal {
 
                     dma::Transfer::start_write(buffer, channel, self)
                 }
+
+                /// Continuously receive data into a circular buffer using DMA.
+                ///
+                /// Together with [`Event::Idle`] this allows receiving frames of unknown length:
+                /// call [`read_to_idle`](dma::CircTransfer::read_to_idle) on the returned
+                /// transfer from the USART interrupt to get all bytes of the last frame.
+                pub fn read_circular<B, C>(
+                    self,
+                    buffer: B,
+                    mut channel: C
+                ) -> dma::CircTransfer<B, C, Self>
+                where
+                    Self: dma::OnChannel<C>,
+                    B: dma::WriteBuffer<Word = u8> + 'static,
+                    C: dma::Channel,
+                {
+                    // NOTE(unsafe) taking the address of a register
+                    let pa = unsafe { &(*$USARTX::ptr()).rdr } as *const _ as u32;
+                    // NOTE(unsafe) usage of a valid peripheral address
+                    unsafe { channel.set_peripheral_address(pa, dma::Increment::Disable) };
+
+                    dma::CircTransfer::start_write(buffer, channel, self)
+                }
+            }
+
+            #[cfg(any(feature = "stm32f302", feature = "stm32f303"))]
+            impl<B, C> dma::Transfer<B, C, Rx<$USARTX>>
+            where
+                B: dma::WriteBuffer<Word = u8>,
+                C: dma::Channel,
+            {
+                /// Is the buffer full, or did the receiver time out or receive the match
+                /// character?
+                ///
+                /// See [`Serial::set_receiver_timeout`] and [`Serial::set_match_character`].
+                pub fn is_frame_complete(&self) -> bool {
+                    // NOTE(unsafe) atomic read with no side effects
+                    let isr = unsafe { (*$USARTX::ptr()).isr.read() };
+                    self.is_complete() || isr.rtof().bit_is_set() || isr.cmf().bit_is_set()
+                }
+
+                /// Block until the frame is complete and return ownership over the transfer's
+                /// parts, together with the number of received bytes
+                ///
+                /// This ends the transfer early if the receiver timed out or received the
+                /// match character. Both flags are cleared.
+                ///
+                /// A reception error stops the transfer as well and is returned instead of
+                /// the number of received bytes.
+                pub fn wait_for_frame(self) -> (B, C, Rx<$USARTX>, Result<usize, Error>) {
+                    // NOTE(unsafe) only reads and stateless writes are performed on the
+                    // register block
+                    let usart = unsafe { &*$USARTX::ptr() };
+
+                    while !self.is_frame_complete() {
+                        if let Some(error) = take_error(usart) {
+                            let (buffer, channel, rx) = self.stop();
+                            return (buffer, channel, rx, Err(error));
+                        }
+                    }
+
+                    // Let the DMA pick up the character that triggered a match
+                    while usart.isr.read().rxne().bit_is_set() && !self.is_complete() {}
+
+                    let remaining = usize::from(self.remaining());
+                    let (mut buffer, channel, mut rx) = self.stop();
+                    rx.clear_receiver_timeout();
+                    rx.clear_character_match();
+
+                    // NOTE(unsafe) only the length of the buffer is used
+                    let (_, len) = unsafe { buffer.write_buffer() };
+
+                    (buffer, channel, rx, Ok(len - remaining))
+                }
+
+                /// Block until the buffer is full or a reception error occurred and return
+                /// ownership over the transfer's parts
+                ///
+                /// Unlike [`wait`](dma::Transfer::wait), this does not hang if a character
+                /// got lost due to an error.
+                pub fn try_wait(self) -> (B, C, Rx<$USARTX>, Result<(), Error>) {
+                    // NOTE(unsafe) only reads and stateless writes are performed on the
+                    // register block
+                    let usart = unsafe { &*$USARTX::ptr() };
+
+                    let result = loop {
+                        if self.is_complete() {
+                            break Ok(());
+                        }
+                        if let Some(error) = take_error(usart) {
+                            break Err(error);
+                        }
+                    };
+
+                    let (buffer, channel, rx) = self.stop();
+                    (buffer, channel, rx, result)
+                }
+            }
+
+            #[cfg(any(feature = "stm32f302", feature = "stm32f303"))]
+            impl<B, C> dma::CircTransfer<B, C, Rx<$USARTX>>
+            where
+                B: dma::WriteBuffer<Word = u8>,
+                C: dma::Channel,
+            {
+                /// If the receive line went idle, clear the idle flag and return all bytes
+                /// received since the last call
+                ///
+                /// The bytes are returned as two slices, as they may wrap around the end of the
+                /// circular buffer.
+                pub fn read_to_idle(&mut self) -> Option<(&[u8], &[u8])> {
+                    // NOTE(unsafe) atomic read with no side effects
+                    let isr = unsafe { (*$USARTX::ptr()).isr.read() };
+                    if isr.idle().bit_is_clear() {
+                        return None;
+                    }
+
+                    // NOTE(unsafe, write) atomic write to a stateless register
+                    unsafe { (*$USARTX::ptr()).icr.write(|w| w.idlecf().clear()) };
+
+                    Some(self.read_available())
+                }
             }
 
             #[cfg(any(feature = "stm32f302", feature = "stm32f303"))]
@@ -400,3 +1580,113 @@ hal! {
     USART2: (usart2, APB1, usart2en, usart2rst, pclk1),
     USART3: (usart3, APB1, usart3en, usart3rst, pclk1),
 }
+
+macro_rules! wakeup {
+    ($(
+        $USARTX:ident: ($usartXsw:ident, $pclkX:ident, $line:expr),
+    )+) => {
+        $(
+            impl<PINS> Serial<$USARTX, PINS> {
+                /// Configures the USART to wake up the MCU from Stop mode
+                ///
+                /// The USART is switched to `clock`, which keeps running in Stop mode, and
+                /// its wakeup interrupt as well as the corresponding EXTI line are enabled.
+                /// The USART interrupt still has to be unmasked in the NVIC.
+                ///
+                /// With [`StopModeClock::Lse`], the baud rate is limited to 2048 Bd.
+                pub fn enable_stop_mode_wakeup(
+                    &mut self,
+                    event: WakeupEvent,
+                    clock: StopModeClock,
+                    baud_rate: Baud,
+                    exti: &mut EXTI,
+                ) {
+                    let frequency = match clock {
+                        StopModeClock::Hsi => 8_000_000,
+                        StopModeClock::Lse => 32_768,
+                    };
+                    let brr = frequency / baud_rate.integer();
+                    crate::assert!(brr >= 16, "impossible baud rate");
+
+                    let wus = match event {
+                        WakeupEvent::AddressMatch => 0b00,
+                        WakeupEvent::StartBit => 0b10,
+                        WakeupEvent::Rxne => 0b11,
+                    };
+
+                    // The clock, BRR and WUS can only be changed while the USART is disabled
+                    self.usart.cr1.modify(|_, w| w.ue().disabled());
+                    // NOTE(unsafe) critical section prevents races, as CFGR3 also
+                    // configures the clocks of other peripherals
+                    cortex_m::interrupt::free(|_| unsafe {
+                        (*RCC::ptr()).cfgr3.modify(|_, w| match clock {
+                            StopModeClock::Hsi => w.$usartXsw().hsi(),
+                            StopModeClock::Lse => w.$usartXsw().lse(),
+                        });
+                    });
+                    // NOTE(write): uses all bits of this register.
+                    self.usart.brr.write(|w| unsafe { w.bits(brr) });
+                    // NOTE(unsafe) WUS is a 2 bit field
+                    self.usart.cr3.modify(|_, w| unsafe {
+                        w.wus().bits(wus);
+                        w.wufie().set_bit()
+                    });
+                    self.usart.cr1.modify(|_, w| {
+                        w.uesm().set_bit();
+                        w.ue().enabled()
+                    });
+
+                    unmask_wakeup_line(exti, $line);
+                }
+
+                /// Stops waking up the MCU from Stop mode and switches the USART back to
+                /// its peripheral clock
+                pub fn disable_stop_mode_wakeup(
+                    &mut self,
+                    baud_rate: Baud,
+                    clocks: Clocks,
+                    exti: &mut EXTI,
+                ) {
+                    let brr = clocks.$pclkX().0 / baud_rate.integer();
+                    crate::assert!(brr >= 16, "impossible baud rate");
+
+                    mask_wakeup_line(exti, $line);
+
+                    self.usart.cr1.modify(|_, w| {
+                        w.uesm().clear_bit();
+                        w.ue().disabled()
+                    });
+                    self.usart.cr3.modify(|_, w| w.wufie().clear_bit());
+                    // NOTE(unsafe) critical section prevents races, as CFGR3 also
+                    // configures the clocks of other peripherals
+                    cortex_m::interrupt::free(|_| unsafe {
+                        (*RCC::ptr()).cfgr3.modify(|_, w| w.$usartXsw().pclk());
+                    });
+                    // NOTE(write): uses all bits of this register.
+                    self.usart.brr.write(|w| unsafe { w.bits(brr) });
+                    self.usart.cr1.modify(|_, w| w.ue().enabled());
+                }
+
+                /// Return true if the wakeup event occurred
+                pub fn is_wakeup(&self) -> bool {
+                    self.usart.isr.read().wuf().bit_is_set()
+                }
+
+                /// Clear the wakeup flag
+                pub fn clear_wakeup(&mut self) {
+                    self.usart.icr.write(|w| w.wucf().clear());
+                }
+            }
+        )+
+    }
+}
+
+wakeup! {
+    USART1: (usart1sw, pclk2, 25),
+}
+
+#[cfg(any(feature = "gpio-f303", feature = "gpio-f303e", feature = "gpio-f373"))]
+wakeup! {
+    USART2: (usart2sw, pclk1, 26),
+    USART3: (usart3sw, pclk1, 28),
+}
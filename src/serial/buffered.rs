@@ -0,0 +1,321 @@
+//! Interrupt driven serial interface with ring buffers
+//!
+//! [`BufferedSerial`] moves received bytes into a receive buffer and sends bytes from a
+//! transmit buffer from within the USART interrupt handler, which has to call
+//! [`on_interrupt`](BufferedSerial::on_interrupt). The [`Read`] and [`Write`] implementations
+//! only access the buffers and never wait for the hardware.
+//!
+//! ```ignore
+//! static mut RX_BUFFER: [u8; 64] = [0; 64];
+//! static mut TX_BUFFER: [u8; 64] = [0; 64];
+//!
+//! let serial = Serial::usart1(dp.USART1, pins, 115_200.Bd(), clocks, &mut rcc.apb2);
+//! // SAFETY: the buffers are only handed out once
+//! let serial = BufferedSerial::new(serial, unsafe { &mut RX_BUFFER }, unsafe { &mut TX_BUFFER });
+//!
+//! // The serial interface is shared with the interrupt handler, e.g. as RTIC resource
+//! #[task(binds = USART1_EXTI25, resources = [serial])]
+//! fn usart1(mut cx: usart1::Context) {
+//!     cx.resources.serial.lock(|serial| serial.on_interrupt());
+//! }
+//! ```
+//!
+//! As the interrupt handler has to run to make progress, never block on the buffered serial
+//! interface (e.g. with `nb::block!`) while holding its lock.
+
+use core::{convert::Infallible, fmt, ops::Deref, ptr};
+
+use crate::{
+    hal::serial::{Read, Write},
+    pac::usart1::RegisterBlock,
+};
+
+use super::{take_error, Error, Serial};
+
+/// Fixed size FIFO queue of bytes on top of a borrowed buffer
+pub struct RingBuffer<'a> {
+    buffer: &'a mut [u8],
+    /// Index of the oldest byte
+    head: usize,
+    /// Number of stored bytes
+    len: usize,
+}
+
+impl<'a> RingBuffer<'a> {
+    /// Create an empty ring buffer, which can hold `buffer.len()` bytes
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        RingBuffer {
+            buffer,
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Maximum number of bytes the ring buffer can hold
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Number of bytes currently stored
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return true if no byte is stored
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Return true if no more bytes can be stored
+    pub fn is_full(&self) -> bool {
+        self.len == self.capacity()
+    }
+
+    /// Append a byte, or give it back if the ring buffer is full
+    pub fn push(&mut self, byte: u8) -> Result<(), u8> {
+        if self.is_full() {
+            return Err(byte);
+        }
+
+        let tail = (self.head + self.len) % self.capacity();
+        self.buffer[tail] = byte;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Remove the oldest byte
+    pub fn pop(&mut self) -> Option<u8> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let byte = self.buffer[self.head];
+        self.head = (self.head + 1) % self.capacity();
+        self.len -= 1;
+        Some(byte)
+    }
+
+    /// Remove all bytes
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+
+    /// Releases the underlying buffer
+    pub fn free(self) -> &'a mut [u8] {
+        self.buffer
+    }
+}
+
+/// Serial interface, which receives and transmits through ring buffers
+pub struct BufferedSerial<USART, PINS> {
+    serial: Serial<USART, PINS>,
+    rx: RingBuffer<'static>,
+    tx: RingBuffer<'static>,
+    error: Option<Error>,
+}
+
+impl<USART, PINS> BufferedSerial<USART, PINS>
+where
+    USART: Deref<Target = RegisterBlock>,
+{
+    /// Wraps a serial interface and starts receiving into `rx_buffer`
+    pub fn new(
+        serial: Serial<USART, PINS>,
+        rx_buffer: &'static mut [u8],
+        tx_buffer: &'static mut [u8],
+    ) -> Self {
+        serial.usart.cr1.modify(|_, w| w.rxneie().set_bit());
+
+        BufferedSerial {
+            serial,
+            rx: RingBuffer::new(rx_buffer),
+            tx: RingBuffer::new(tx_buffer),
+            error: None,
+        }
+    }
+
+    /// Stops the interrupt driven operation and releases the serial interface and buffers
+    ///
+    /// Bytes still stored in the buffers are dropped.
+    pub fn free(self) -> (Serial<USART, PINS>, &'static mut [u8], &'static mut [u8]) {
+        self.serial.usart.cr1.modify(|_, w| {
+            w.rxneie().clear_bit();
+            w.txeie().clear_bit()
+        });
+
+        (self.serial, self.rx.free(), self.tx.free())
+    }
+
+    /// Handles the USART interrupt
+    ///
+    /// This has to be called from the interrupt handler of the USART.
+    pub fn on_interrupt(&mut self) {
+        let usart = &self.serial.usart;
+
+        // Reception errors are reported by the next call of `read`. Clearing them is
+        // required anyway, as an overrun would otherwise trigger the interrupt forever.
+        while let Some(error) = take_error(usart) {
+            self.error.get_or_insert(error);
+        }
+
+        let isr = usart.isr.read();
+        if isr.rxne().bit_is_set() {
+            // NOTE(read_volatile) 8-bit read that's not possible through the svd2rust API
+            let byte = unsafe { ptr::read_volatile(&usart.rdr as *const _ as *const u8) };
+            if self.rx.push(byte).is_err() {
+                self.error.get_or_insert(Error::Overrun);
+            }
+        }
+
+        if isr.txe().bit_is_set() && usart.cr1.read().txeie().bit_is_set() {
+            match self.tx.pop() {
+                // NOTE(write_volatile) 8-bit write that's not possible through the svd2rust API
+                Some(byte) => unsafe {
+                    ptr::write_volatile(&usart.tdr as *const _ as *mut u8, byte)
+                },
+                None => usart.cr1.modify(|_, w| w.txeie().clear_bit()),
+            }
+        }
+    }
+
+    /// Number of received bytes, which can be read without blocking
+    pub fn available(&self) -> usize {
+        self.rx.len()
+    }
+}
+
+impl<USART, PINS> Read<u8> for BufferedSerial<USART, PINS>
+where
+    USART: Deref<Target = RegisterBlock>,
+{
+    /// Besides the reception errors of [`Serial`], [`Error::Overrun`] is also returned, if
+    /// bytes were dropped because the receive buffer was full.
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u8, Error> {
+        if let Some(error) = self.error.take() {
+            return Err(nb::Error::Other(error));
+        }
+        self.rx.pop().ok_or(nb::Error::WouldBlock)
+    }
+}
+
+impl<USART, PINS> Write<u8> for BufferedSerial<USART, PINS>
+where
+    USART: Deref<Target = RegisterBlock>,
+{
+    type Error = Infallible;
+
+    fn flush(&mut self) -> nb::Result<(), Infallible> {
+        if self.tx.is_empty() && self.serial.usart.isr.read().tc().bit_is_set() {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    fn write(&mut self, byte: u8) -> nb::Result<(), Infallible> {
+        self.tx.push(byte).map_err(|_| nb::Error::WouldBlock)?;
+        self.serial.usart.cr1.modify(|_, w| w.txeie().set_bit());
+        Ok(())
+    }
+}
+
+/// Writes the string into the transmit buffer
+///
+/// Returns an error without writing anything, if the string does not fit into the free part
+/// of the transmit buffer.
+impl<USART, PINS> fmt::Write for BufferedSerial<USART, PINS>
+where
+    USART: Deref<Target = RegisterBlock>,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.tx.capacity() - self.tx.len() < s.len() {
+            return Err(fmt::Error);
+        }
+
+        for &byte in s.as_bytes() {
+            // Can't fail, as the free space was checked above
+            let _ = self.tx.push(byte);
+        }
+        self.serial.usart.cr1.modify(|_, w| w.txeie().set_bit());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fifo_order() {
+        let mut buffer = [0; 4];
+        let mut ring = RingBuffer::new(&mut buffer);
+
+        assert!(ring.is_empty());
+        assert_eq!(ring.pop(), None);
+
+        ring.push(1).unwrap();
+        ring.push(2).unwrap();
+        ring.push(3).unwrap();
+        assert_eq!(ring.len(), 3);
+        assert_eq!(ring.pop(), Some(1));
+        assert_eq!(ring.pop(), Some(2));
+        assert_eq!(ring.pop(), Some(3));
+        assert_eq!(ring.pop(), None);
+    }
+
+    #[test]
+    fn full() {
+        let mut buffer = [0; 2];
+        let mut ring = RingBuffer::new(&mut buffer);
+
+        ring.push(1).unwrap();
+        ring.push(2).unwrap();
+        assert!(ring.is_full());
+        assert_eq!(ring.push(3), Err(3));
+        assert_eq!(ring.pop(), Some(1));
+        ring.push(3).unwrap();
+        assert_eq!(ring.pop(), Some(2));
+        assert_eq!(ring.pop(), Some(3));
+    }
+
+    #[test]
+    fn wrap_around() {
+        let mut buffer = [0; 3];
+        let mut ring = RingBuffer::new(&mut buffer);
+
+        for byte in 0..10 {
+            ring.push(byte).unwrap();
+            ring.push(byte + 100).unwrap();
+            assert_eq!(ring.pop(), Some(byte));
+            assert_eq!(ring.pop(), Some(byte + 100));
+            assert!(ring.is_empty());
+        }
+    }
+
+    #[test]
+    fn clear() {
+        let mut buffer = [0; 3];
+        let mut ring = RingBuffer::new(&mut buffer);
+
+        ring.push(1).unwrap();
+        ring.push(2).unwrap();
+        ring.clear();
+        assert!(ring.is_empty());
+        ring.push(3).unwrap();
+        assert_eq!(ring.pop(), Some(3));
+    }
+
+    #[test]
+    fn zero_capacity() {
+        let mut buffer = [];
+        let mut ring = RingBuffer::new(&mut buffer);
+
+        assert!(ring.is_empty());
+        assert!(ring.is_full());
+        assert_eq!(ring.push(1), Err(1));
+        assert_eq!(ring.pop(), None);
+    }
+}
@@ -0,0 +1,177 @@
+//! IrDA serial infrared (SIR) mode
+//!
+//! In IrDA mode, the USART encodes each zero bit as a short pulse on the TX pin and decodes
+//! such pulses on the RX pin, as required by IrDA transceivers. The link is half-duplex: the
+//! transmitter and receiver must not be used at the same time.
+
+use core::{convert::Infallible, ptr};
+
+use crate::{
+    hal::{blocking, serial},
+    rcc::Clocks,
+    time::rate::*,
+};
+
+use super::{init, swapped, take_error, Error, Instance, RxPin, TxPin};
+
+/// Frequency from which the pulse width is derived in low-power mode
+const LOW_POWER_FREQUENCY: u32 = 1_843_200;
+
+/// IrDA power mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IrdaPower {
+    /// Pulses of 3/16 bit times
+    Normal,
+    /// Pulses of 3 periods of a 1.8432 MHz clock, independent of the baud rate
+    LowPower,
+}
+
+/// IrDA configuration
+pub struct IrdaConfig {
+    power: IrdaPower,
+}
+
+impl IrdaConfig {
+    /// Create a new `IrdaConfig` using the settings of `IrdaConfig::default()`.
+    pub fn new() -> IrdaConfig {
+        IrdaConfig::default()
+    }
+
+    /// Set the power mode.
+    pub fn power(mut self, power: IrdaPower) -> Self {
+        self.power = power;
+        self
+    }
+}
+
+impl Default for IrdaConfig {
+    fn default() -> Self {
+        IrdaConfig {
+            power: IrdaPower::Normal,
+        }
+    }
+}
+
+/// Serial abstraction for IrDA SIR communication
+pub struct Irda<USART, PINS> {
+    usart: USART,
+    pins: PINS,
+}
+
+impl<USART, TX, RX> Irda<USART, (TX, RX)>
+where
+    USART: Instance,
+{
+    /// Configures a USART peripheral for IrDA SIR communication
+    ///
+    /// The baud rate is limited to 115200 Bd by the IrDA SIR specification.
+    pub fn new(
+        usart: USART,
+        pins: (TX, RX),
+        config: IrdaConfig,
+        baud_rate: Baud,
+        clocks: Clocks,
+        apb: &mut USART::APB,
+    ) -> Self
+    where
+        TX: TxPin<USART>,
+        RX: RxPin<USART>,
+    {
+        init(&usart, baud_rate, clocks, apb);
+
+        let psc = match config.power {
+            // A prescaler of 1 is required in normal mode
+            IrdaPower::Normal => 1,
+            IrdaPower::LowPower => {
+                let pclk = USART::clock(&clocks).0;
+                let psc = (pclk + LOW_POWER_FREQUENCY / 2) / LOW_POWER_FREQUENCY;
+                crate::assert!(0 < psc && psc < 256, "impossible low-power frequency");
+                psc as u8
+            }
+        };
+
+        let swap = swapped::<USART, TX, RX>();
+        // NOTE(unsafe) the prescaler is checked to fit into 8 bits
+        usart.gtpr.modify(|_, w| unsafe { w.psc().bits(psc) });
+        // IrDA mode requires LINEN, STOP, CLKEN, SCEN and HDSEL to be cleared. All of them can
+        // only be written while the USART is disabled, so before setting UE.
+        usart.cr2.modify(|_, w| {
+            w.swap().bit(swap);
+            w.linen().clear_bit();
+            w.stop().stop1();
+            w.clken().clear_bit()
+        });
+        usart.cr3.modify(|_, w| {
+            w.scen().clear_bit();
+            w.hdsel().clear_bit();
+            w.irlp().bit(config.power == IrdaPower::LowPower);
+            w.iren().set_bit()
+        });
+        usart.cr1.modify(|_, w| {
+            w.ue().enabled();
+            w.re().enabled();
+            w.te().enabled()
+        });
+
+        Irda { usart, pins }
+    }
+}
+
+impl<USART, PINS> Irda<USART, PINS>
+where
+    USART: Instance,
+{
+    /// Releases the USART peripheral and associated pins
+    pub fn free(self) -> (USART, PINS) {
+        self.usart.cr1.modify(|_, w| w.ue().disabled());
+        self.usart.cr3.modify(|_, w| w.iren().clear_bit());
+
+        (self.usart, self.pins)
+    }
+}
+
+impl<USART, PINS> serial::Read<u8> for Irda<USART, PINS>
+where
+    USART: Instance,
+{
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u8, Error> {
+        Err(if let Some(error) = take_error(&self.usart) {
+            nb::Error::Other(error)
+        } else if self.usart.isr.read().rxne().bit_is_set() {
+            // NOTE(read_volatile) 8-bit read that's not possible through the svd2rust API
+            return Ok(unsafe { ptr::read_volatile(&self.usart.rdr as *const _ as *const _) });
+        } else {
+            nb::Error::WouldBlock
+        })
+    }
+}
+
+impl<USART, PINS> serial::Write<u8> for Irda<USART, PINS>
+where
+    USART: Instance,
+{
+    type Error = Infallible;
+
+    fn flush(&mut self) -> nb::Result<(), Infallible> {
+        if self.usart.isr.read().tc().bit_is_set() {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    fn write(&mut self, byte: u8) -> nb::Result<(), Infallible> {
+        if self.usart.isr.read().txe().bit_is_set() {
+            // NOTE(unsafe) atomic write to stateless register
+            // NOTE(write_volatile) 8-bit write that's not possible through the svd2rust API
+            unsafe { ptr::write_volatile(&self.usart.tdr as *const _ as *mut _, byte) }
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+impl<USART, PINS> blocking::serial::write::Default<u8> for Irda<USART, PINS> where USART: Instance {}
@@ -0,0 +1,332 @@
+//! Local Interconnect Network (LIN)
+//!
+//! [`Lin`] puts a [`Serial`] into LIN mode and implements the frame level protocol on top of
+//! it. A LIN frame consists of a header, which is always sent by the master, and a response,
+//! which is sent by either the master or one of the slaves:
+//!
+//! ```text
+//! | break | sync (0x55) | protected identifier | data (1 to 8 bytes) | checksum |
+//! ```
+//!
+//! The LIN transceiver loops the transmitted signal back to the receiver, so every transmitted
+//! byte is read back and compared. This detects bus collisions, but also means that the
+//! blocking methods of [`Lin`] never return if the loopback is missing.
+//!
+//! The protected identifier ([`Pid`]) and [`checksum`] logic is independent of the hardware.
+
+use core::{convert::Infallible, ops::Deref};
+
+use crate::{
+    hal::serial::{Read, Write},
+    pac::usart1::RegisterBlock,
+};
+
+use super::{Error as SerialError, Serial};
+
+/// Value of the sync field following the break field of each header
+pub const SYNC: u8 = 0x55;
+
+/// LIN error
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Error of the underlying serial interface
+    Serial(SerialError),
+    /// The sync field of a header was not [`SYNC`]
+    Sync,
+    /// The parity bits of a protected identifier were wrong
+    Parity,
+    /// The checksum of a response did not match its data
+    Checksum,
+    /// A transmitted byte was not read back unchanged from the bus
+    Readback,
+}
+
+impl From<SerialError> for Error {
+    fn from(error: SerialError) -> Self {
+        Error::Serial(error)
+    }
+}
+
+/// Minimum length of a low level on the bus to be detected as break
+pub enum BreakDetection {
+    /// 10 bit times
+    Bits10,
+    /// 11 bit times
+    Bits11,
+}
+
+/// Checksum model of a frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChecksumModel {
+    /// Checksum over the data bytes only (LIN 1.x and diagnostic frames)
+    Classic,
+    /// Checksum over the protected identifier and the data bytes (LIN 2.x)
+    Enhanced,
+}
+
+/// Protected identifier, a 6 bit frame identifier together with its two parity bits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pid(u8);
+
+impl Pid {
+    /// Calculate the protected identifier of the frame identifier `id`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` does not fit into 6 bits.
+    pub fn from_id(id: u8) -> Self {
+        crate::assert!(id < 0x40);
+
+        let bit = |n: u8| (id >> n) & 1;
+        let p0 = bit(0) ^ bit(1) ^ bit(2) ^ bit(4);
+        let p1 = !(bit(1) ^ bit(3) ^ bit(4) ^ bit(5)) & 1;
+
+        Pid(id | (p0 << 6) | (p1 << 7))
+    }
+
+    /// Check the parity bits of a received protected identifier
+    pub fn from_raw(raw: u8) -> Result<Self, Error> {
+        let pid = Pid::from_id(raw & 0x3F);
+        if pid.0 == raw {
+            Ok(pid)
+        } else {
+            Err(Error::Parity)
+        }
+    }
+
+    /// The frame identifier without parity bits
+    pub fn id(self) -> u8 {
+        self.0 & 0x3F
+    }
+
+    /// The protected identifier as transmitted on the bus
+    pub fn raw(self) -> u8 {
+        self.0
+    }
+}
+
+/// Calculate the checksum of a response
+///
+/// Note that the diagnostic frames (identifiers `0x3C` and `0x3D`) always use the
+/// [classic](ChecksumModel::Classic) checksum model.
+pub fn checksum(model: ChecksumModel, pid: Pid, data: &[u8]) -> u8 {
+    let init = match model {
+        ChecksumModel::Classic => 0,
+        ChecksumModel::Enhanced => u16::from(pid.raw()),
+    };
+
+    // Sum with carry: every overflow is added back to the sum
+    let sum = data.iter().fold(init, |sum, &byte| {
+        let sum = sum + u16::from(byte);
+        if sum > 0xFF {
+            sum - 0xFF
+        } else {
+            sum
+        }
+    });
+
+    !(sum as u8)
+}
+
+/// Serial interface operating in LIN mode
+pub struct Lin<USART, PINS> {
+    serial: Serial<USART, PINS>,
+}
+
+impl<USART, PINS> Lin<USART, PINS>
+where
+    USART: Deref<Target = RegisterBlock>,
+    Serial<USART, PINS>: Read<u8, Error = SerialError> + Write<u8, Error = Infallible>,
+{
+    /// Switches a serial interface into LIN mode
+    ///
+    /// LIN mode requires one stop bit and 8 data bits without parity, which is the default
+    /// configuration of [`Serial`].
+    pub fn new(serial: Serial<USART, PINS>, break_detection: BreakDetection) -> Self {
+        let usart = &serial.usart;
+
+        // LINEN and LBDL can only be written while the USART is disabled
+        usart.cr1.modify(|_, w| w.ue().disabled());
+        usart.cr2.modify(|_, w| {
+            match break_detection {
+                BreakDetection::Bits10 => w.lbdl().clear_bit(),
+                BreakDetection::Bits11 => w.lbdl().set_bit(),
+            };
+            w.clken().clear_bit();
+            w.linen().set_bit()
+        });
+        usart.cr3.modify(|_, w| {
+            w.scen().clear_bit();
+            w.hdsel().clear_bit();
+            w.iren().clear_bit()
+        });
+        usart.cr1.modify(|_, w| w.ue().enabled());
+
+        Lin { serial }
+    }
+
+    /// Leaves LIN mode and releases the serial interface
+    pub fn free(self) -> Serial<USART, PINS> {
+        let usart = &self.serial.usart;
+
+        usart.cr1.modify(|_, w| w.ue().disabled());
+        usart.cr2.modify(|_, w| {
+            w.lbdie().clear_bit();
+            w.linen().clear_bit()
+        });
+        usart.cr1.modify(|_, w| w.ue().enabled());
+
+        self.serial
+    }
+
+    /// Starts listening for the break detection interrupt
+    pub fn listen_break(&mut self) {
+        self.serial.usart.cr2.modify(|_, w| w.lbdie().set_bit());
+    }
+
+    /// Stops listening for the break detection interrupt
+    pub fn unlisten_break(&mut self) {
+        self.serial.usart.cr2.modify(|_, w| w.lbdie().clear_bit());
+    }
+
+    /// Return true if a break has been detected on the bus
+    pub fn is_break_detected(&self) -> bool {
+        self.serial.usart.isr.read().lbdf().bit_is_set()
+    }
+
+    /// Clear the break detection flag
+    pub fn clear_break(&mut self) {
+        self.serial.usart.icr.write(|w| w.lbdcf().clear());
+    }
+
+    /// Transmits a break field after the current character
+    pub fn send_break(&mut self) {
+        self.serial.usart.rqr.write(|w| w.sbkrq().set_bit());
+    }
+
+    /// Sends a frame header, consisting of break, sync field and protected identifier
+    ///
+    /// This is only done by the master node.
+    pub fn send_header(&mut self, pid: Pid) -> Result<(), Error> {
+        self.send_break();
+        while !self.is_break_detected() {}
+        self.discard_break();
+
+        self.write_byte(SYNC)?;
+        self.write_byte(pid.raw())
+    }
+
+    /// Receives the rest of a frame header, after a break has been detected
+    ///
+    /// See [`is_break_detected`](Lin::is_break_detected) and
+    /// [`listen_break`](Lin::listen_break).
+    pub fn read_header(&mut self) -> Result<Pid, Error> {
+        self.discard_break();
+
+        if self.read_byte()? != SYNC {
+            return Err(Error::Sync);
+        }
+        Pid::from_raw(self.read_byte()?)
+    }
+
+    /// Sends the response of the frame `pid`, followed by its checksum
+    pub fn write_response(
+        &mut self,
+        pid: Pid,
+        data: &[u8],
+        model: ChecksumModel,
+    ) -> Result<(), Error> {
+        for &byte in data {
+            self.write_byte(byte)?;
+        }
+        self.write_byte(checksum(model, pid, data))
+    }
+
+    /// Receives the response of the frame `pid` into `buffer` and verifies its checksum
+    pub fn read_response(
+        &mut self,
+        pid: Pid,
+        buffer: &mut [u8],
+        model: ChecksumModel,
+    ) -> Result<(), Error> {
+        for byte in buffer.iter_mut() {
+            *byte = self.read_byte()?;
+        }
+
+        if self.read_byte()? == checksum(model, pid, buffer) {
+            Ok(())
+        } else {
+            Err(Error::Checksum)
+        }
+    }
+
+    /// Drops the all-zero character which the receiver gets with each break
+    fn discard_break(&mut self) {
+        self.clear_break();
+        super::clear_errors(&self.serial.usart);
+        self.serial.usart.rqr.write(|w| w.rxfrq().set_bit());
+    }
+
+    fn read_byte(&mut self) -> Result<u8, Error> {
+        Ok(nb::block!(self.serial.read())?)
+    }
+
+    fn write_byte(&mut self, byte: u8) -> Result<(), Error> {
+        match nb::block!(self.serial.write(byte)) {
+            Ok(()) => {}
+            Err(infallible) => match infallible {},
+        }
+
+        if self.read_byte()? == byte {
+            Ok(())
+        } else {
+            Err(Error::Readback)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pid_parity() {
+        assert_eq!(Pid::from_id(0x00).raw(), 0x80);
+        assert_eq!(Pid::from_id(0x01).raw(), 0xC1);
+        assert_eq!(Pid::from_id(0x10).raw(), 0x50);
+        assert_eq!(Pid::from_id(0x3C).raw(), 0x3C);
+        assert_eq!(Pid::from_id(0x3D).raw(), 0x7D);
+        assert_eq!(Pid::from_id(0x3F).raw(), 0xBF);
+    }
+
+    #[test]
+    fn pid_from_raw() {
+        assert_eq!(Pid::from_raw(0x7D).unwrap().id(), 0x3D);
+        assert!(matches!(Pid::from_raw(0x3D), Err(Error::Parity)));
+        assert!(matches!(Pid::from_raw(0xFD), Err(Error::Parity)));
+    }
+
+    #[test]
+    fn classic_checksum() {
+        // Example from the LIN 2.2A specification
+        let data = [0x4A, 0x55, 0x93, 0xE5];
+        assert_eq!(
+            checksum(ChecksumModel::Classic, Pid::from_id(0x23), &data),
+            0xE6
+        );
+    }
+
+    #[test]
+    fn enhanced_checksum() {
+        let data = [0x4A, 0x55, 0x93, 0xE5];
+        assert_eq!(
+            checksum(ChecksumModel::Enhanced, Pid::from_id(0x23), &data),
+            0x43
+        );
+        assert_eq!(
+            checksum(ChecksumModel::Enhanced, Pid::from_id(0x23), &[]),
+            !0xA3
+        );
+    }
+}
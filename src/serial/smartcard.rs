@@ -0,0 +1,408 @@
+//! Smartcard (ISO 7816-3) mode
+//!
+//! In smartcard mode, the USART exchanges characters with the card over a single open-drain
+//! I/O line, while the card is clocked from the CK pin. Each character has 8 data bits and an
+//! even parity bit. A receiver signals a parity error by pulling the line low during the
+//! stop bits (NACK), after which the character is retransmitted. This is handled by the
+//! hardware on both sides.
+//!
+//! The reset (RST) and power lines of the card are ordinary GPIO pins, which have to be
+//! controlled by the user. After releasing the reset of the card, the answer to reset is read
+//! with [`read_atr`](Smartcard::read_atr). Commands are then sent with the T=0 protocol using
+//! [`command`](Smartcard::command).
+
+use core::ptr;
+
+use crate::{
+    hal::{blocking, serial},
+    rcc::Clocks,
+    time::rate::*,
+};
+
+use super::{clear_errors, take_error, CkPin, Error as SerialError, HalfDuplexPin, Instance};
+
+/// Maximum length of an answer to reset, including the initial character
+pub const MAX_ATR_LENGTH: usize = 33;
+
+/// Default elementary time unit (bit time) in card clock cycles
+const DEFAULT_ETU: u32 = 372;
+
+/// Procedure byte requesting more waiting time
+const NULL: u8 = 0x60;
+
+/// Smartcard error
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Error of the underlying serial interface
+    ///
+    /// [`SerialError::Parity`] is returned for a character, which was still received with a
+    /// parity error after all retries. [`SerialError::Framing`] is returned for a character,
+    /// which was rejected by the card after all retransmissions.
+    Serial(SerialError),
+    /// The answer to reset was malformed
+    Atr,
+    /// The card sent an unexpected procedure byte
+    Procedure,
+}
+
+impl From<SerialError> for Error {
+    fn from(error: SerialError) -> Self {
+        Error::Serial(error)
+    }
+}
+
+/// Smartcard configuration
+pub struct SmartcardConfig {
+    clock: Hertz,
+    guard_time: u8,
+    retries: u8,
+    nack: bool,
+}
+
+impl SmartcardConfig {
+    /// Create a new `SmartcardConfig` using the settings of `SmartcardConfig::default()`.
+    pub fn new() -> SmartcardConfig {
+        SmartcardConfig::default()
+    }
+
+    /// Set the maximum frequency of the clock output to the card.
+    ///
+    /// The actual frequency is the USART clock divided by an even number of at most 62.
+    pub fn clock(mut self, clock: Hertz) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Set the guard time, the number of extra bit times after each transmitted character.
+    ///
+    /// This is the extra guard time N announced by the card in its answer to reset.
+    pub fn guard_time(mut self, guard_time: u8) -> Self {
+        self.guard_time = guard_time;
+        self
+    }
+
+    /// Set the number of retransmissions and receptions of a character after a parity error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `retries` is larger than 7.
+    pub fn retries(mut self, retries: u8) -> Self {
+        crate::assert!(retries < 8);
+        self.retries = retries;
+        self
+    }
+
+    /// Set whether received characters with parity error are rejected with a NACK.
+    pub fn nack(mut self, nack: bool) -> Self {
+        self.nack = nack;
+        self
+    }
+}
+
+impl Default for SmartcardConfig {
+    fn default() -> Self {
+        SmartcardConfig {
+            clock: Hertz(3_571_200),
+            guard_time: 0,
+            retries: 3,
+            nack: true,
+        }
+    }
+}
+
+/// Serial abstraction for smartcard communication
+pub struct Smartcard<USART, PINS> {
+    usart: USART,
+    pins: PINS,
+    /// USART clock cycles per card clock cycle
+    divider: u32,
+}
+
+impl<USART, IO, CK> Smartcard<USART, (IO, CK)>
+where
+    USART: Instance,
+{
+    /// Configures a USART peripheral for smartcard communication
+    ///
+    /// The I/O pin has to be configured as open-drain output with a pull-up. The bit time is
+    /// set to the default of 372 card clock cycles.
+    pub fn new(
+        usart: USART,
+        pins: (IO, CK),
+        config: SmartcardConfig,
+        clocks: Clocks,
+        apb: &mut USART::APB,
+    ) -> Self
+    where
+        IO: HalfDuplexPin<USART>,
+        CK: CkPin<USART>,
+    {
+        USART::enable_clock(apb);
+
+        // The card clock is the USART clock divided by 2 * PSC
+        let pclk = USART::clock(&clocks).0;
+        let psc = (pclk + 2 * config.clock.0 - 1) / (2 * config.clock.0);
+        crate::assert!(0 < psc && psc < 32, "impossible smartcard clock");
+        let divider = 2 * psc;
+
+        // NOTE(write): uses all bits of this register.
+        usart
+            .brr
+            .write(|w| unsafe { w.bits(DEFAULT_ETU * divider) });
+        // NOTE(unsafe) the prescaler is checked to fit into 5 bits
+        usart.gtpr.write(|w| unsafe {
+            w.gt().bits(config.guard_time);
+            w.psc().bits(psc as u8)
+        });
+        // Smartcard mode requires 9 bit characters with even parity, 1.5 stop bits and
+        // LINEN, IREN and HDSEL to be cleared. All of them can only be written while the
+        // USART is disabled, so before setting UE.
+        usart.cr1.modify(|_, w| {
+            w.m1().clear_bit();
+            w.m0().set_bit();
+            w.pce().set_bit();
+            w.ps().clear_bit()
+        });
+        usart.cr2.modify(|_, w| {
+            w.linen().clear_bit();
+            w.stop().stop1p5();
+            w.clken().set_bit()
+        });
+        // NOTE(unsafe) the number of retries is checked to fit into 3 bits
+        usart.cr3.modify(|_, w| unsafe {
+            w.iren().clear_bit();
+            w.hdsel().clear_bit();
+            w.scarcnt().bits(config.retries);
+            w.nack().bit(config.nack);
+            w.scen().set_bit()
+        });
+        usart.cr1.modify(|_, w| {
+            w.ue().enabled();
+            w.re().enabled();
+            w.te().enabled()
+        });
+
+        Smartcard {
+            usart,
+            pins,
+            divider,
+        }
+    }
+}
+
+impl<USART, PINS> Smartcard<USART, PINS>
+where
+    USART: Instance,
+{
+    /// Sets the elementary time unit (bit time) in card clock cycles
+    ///
+    /// The card supports other values than the default of 372 if announced in its answer to
+    /// reset, which are selected by a protocol and parameter selection (PPS) exchange.
+    pub fn set_etu(&mut self, cycles: u16) {
+        let brr = u32::from(cycles) * self.divider;
+        crate::assert!(
+            16 <= brr && brr < 0x1_0000,
+            "impossible elementary time unit"
+        );
+
+        // BRR can only be written while the USART is disabled
+        self.usart.cr1.modify(|_, w| w.ue().disabled());
+        // NOTE(write): uses all bits of this register.
+        self.usart.brr.write(|w| unsafe { w.bits(brr) });
+        self.usart.cr1.modify(|_, w| w.ue().enabled());
+    }
+
+    /// Receives the answer to reset into `buffer` and returns its length
+    ///
+    /// This has to be called right after releasing the reset of the card. The inverse
+    /// convention announced by the initial character is configured automatically.
+    pub fn read_atr(&mut self, buffer: &mut [u8; MAX_ATR_LENGTH]) -> Result<usize, Error> {
+        buffer[0] = match self.read_byte()? {
+            0x3B => 0x3B,
+            // 0x3F sent with inverse convention, but received with direct convention
+            0x03 => {
+                self.usart.cr1.modify(|_, w| w.ue().disabled());
+                self.usart.cr2.modify(|_, w| {
+                    w.datainv().set_bit();
+                    w.msbfirst().set_bit()
+                });
+                self.usart.cr1.modify(|_, w| w.ue().enabled());
+                0x3F
+            }
+            _ => return Err(Error::Atr),
+        };
+        let mut len = 1;
+
+        // The format byte T0 and the TDi bytes announce the following interface bytes
+        let t0 = self.read_atr_byte(buffer, &mut len)?;
+        let mut indicator = t0 >> 4;
+        let mut checksum_present = false;
+        loop {
+            for _ in 0..(indicator & 0b0111).count_ones() {
+                self.read_atr_byte(buffer, &mut len)?;
+            }
+            if indicator & 0b1000 == 0 {
+                break;
+            }
+            let td = self.read_atr_byte(buffer, &mut len)?;
+            // A checksum is present if any protocol other than T=0 is announced
+            checksum_present |= td & 0x0F != 0;
+            indicator = td >> 4;
+        }
+
+        // Historical bytes
+        for _ in 0..(t0 & 0x0F) {
+            self.read_atr_byte(buffer, &mut len)?;
+        }
+
+        if checksum_present {
+            self.read_atr_byte(buffer, &mut len)?;
+            // The XOR over all bytes from T0 to the checksum TCK is zero
+            if buffer[1..len].iter().fold(0, |tck, byte| tck ^ byte) != 0 {
+                return Err(Error::Atr);
+            }
+        }
+
+        Ok(len)
+    }
+
+    /// Executes a T=0 command and returns the number of received bytes and the status words
+    ///
+    /// The command `header` consists of CLA, INS, P1, P2 and P3. If `data` is not empty, it
+    /// is sent to the card, otherwise up to `response.len()` bytes are received into
+    /// `response`. P3 has to match the length of the sent or expected data.
+    pub fn command(
+        &mut self,
+        header: [u8; 5],
+        data: &[u8],
+        response: &mut [u8],
+    ) -> Result<(usize, [u8; 2]), Error> {
+        let ins = header[1];
+        let outgoing = !data.is_empty();
+        let len = if outgoing { data.len() } else { response.len() };
+        let mut index = 0;
+
+        for &byte in &header {
+            self.write_byte(byte)?;
+        }
+
+        loop {
+            let procedure = self.read_byte()?;
+            let count = if procedure == NULL {
+                continue;
+            } else if procedure & 0xF0 == 0x60 || procedure & 0xF0 == 0x90 {
+                // SW1 is followed by SW2
+                let sw2 = self.read_byte()?;
+                let received = if outgoing { 0 } else { index };
+                return Ok((received, [procedure, sw2]));
+            } else if procedure == ins {
+                // Exchange all remaining bytes
+                len - index
+            } else if procedure == !ins {
+                // Exchange the next byte only
+                1
+            } else {
+                return Err(Error::Procedure);
+            };
+
+            if index + count > len {
+                return Err(Error::Procedure);
+            }
+            for _ in 0..count {
+                if outgoing {
+                    self.write_byte(data[index])?;
+                } else {
+                    response[index] = self.read_byte()?;
+                }
+                index += 1;
+            }
+        }
+    }
+
+    /// Releases the USART peripheral and associated pins
+    pub fn free(self) -> (USART, PINS) {
+        self.usart.cr1.modify(|_, w| w.ue().disabled());
+        self.usart.cr2.modify(|_, w| w.clken().clear_bit());
+        self.usart.cr3.modify(|_, w| w.scen().clear_bit());
+
+        (self.usart, self.pins)
+    }
+
+    fn read_atr_byte(&mut self, buffer: &mut [u8], len: &mut usize) -> Result<u8, Error> {
+        let byte = self.read_byte()?;
+        *buffer.get_mut(*len).ok_or(Error::Atr)? = byte;
+        *len += 1;
+        Ok(byte)
+    }
+
+    fn read_byte(&mut self) -> Result<u8, Error> {
+        Ok(nb::block!(serial::Read::read(self))?)
+    }
+
+    fn write_byte(&mut self, byte: u8) -> Result<(), Error> {
+        nb::block!(serial::Write::write(self, byte))?;
+        Ok(nb::block!(serial::Write::flush(self))?)
+    }
+}
+
+impl<USART, PINS> serial::Read<u8> for Smartcard<USART, PINS>
+where
+    USART: Instance,
+{
+    type Error = SerialError;
+
+    fn read(&mut self) -> nb::Result<u8, SerialError> {
+        Err(if let Some(error) = take_error(&self.usart) {
+            nb::Error::Other(error)
+        } else if self.usart.isr.read().rxne().bit_is_set() {
+            // NOTE(read_volatile) 8-bit read that's not possible through the svd2rust API
+            return Ok(unsafe { ptr::read_volatile(&self.usart.rdr as *const _ as *const _) });
+        } else {
+            nb::Error::WouldBlock
+        })
+    }
+}
+
+impl<USART, PINS> serial::Write<u8> for Smartcard<USART, PINS>
+where
+    USART: Instance,
+{
+    type Error = SerialError;
+
+    /// Waits until the transmission is complete
+    ///
+    /// Returns [`SerialError::Framing`] if the card rejected a character after all
+    /// retransmissions.
+    fn flush(&mut self) -> nb::Result<(), SerialError> {
+        let isr = self.usart.isr.read();
+
+        if isr.fe().bit_is_set() {
+            self.usart.icr.write(|w| w.fecf().clear());
+            Err(nb::Error::Other(SerialError::Framing))
+        } else if isr.tc().bit_is_set() {
+            // The receiver sees the transmitted characters on the shared line, drop them
+            self.usart.rqr.write(|w| w.rxfrq().set_bit());
+            clear_errors(&self.usart);
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    fn write(&mut self, byte: u8) -> nb::Result<(), SerialError> {
+        if self.usart.isr.read().txe().bit_is_set() {
+            // NOTE(unsafe) atomic write to stateless register
+            // NOTE(write_volatile) 8-bit write that's not possible through the svd2rust API
+            unsafe { ptr::write_volatile(&self.usart.tdr as *const _ as *mut _, byte) }
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+impl<USART, PINS> blocking::serial::write::Default<u8> for Smartcard<USART, PINS> where
+    USART: Instance
+{
+}
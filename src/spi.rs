@@ -24,7 +24,7 @@ use crate::pac::{
 use crate::pac::SPI4;
 
 #[cfg(any(feature = "stm32f373", feature = "stm32f378"))]
-use crate::gpio::gpioa::{PA1, PA10, PA12, PA13, PA2, PA3, PA8, PA9};
+use crate::gpio::gpioa::{PA1, PA10, PA11, PA12, PA13, PA2, PA3, PA8, PA9};
 #[cfg(any(
     feature = "stm32f302x6",
     feature = "stm32f302x8",
@@ -36,7 +36,9 @@ use crate::gpio::gpioa::{PA1, PA10, PA12, PA13, PA2, PA3, PA8, PA9};
     feature = "stm32f398",
 ))]
 use crate::gpio::gpioa::{PA10, PA11};
-use crate::gpio::gpioa::{PA5, PA6, PA7};
+use crate::gpio::gpioa::{PA15, PA4, PA5, PA6, PA7};
+#[cfg(any(feature = "stm32f373", feature = "stm32f378"))]
+use crate::gpio::gpiob::{PB0, PB10, PB8, PB9};
 #[cfg(any(
     feature = "stm32f301",
     feature = "stm32f302",
@@ -46,9 +48,7 @@ use crate::gpio::gpioa::{PA5, PA6, PA7};
     feature = "stm32f358",
     feature = "stm32f398"
 ))]
-use crate::gpio::gpiob::PB13;
-#[cfg(any(feature = "stm32f373", feature = "stm32f378"))]
-use crate::gpio::gpiob::{PB0, PB10, PB8};
+use crate::gpio::gpiob::{PB12, PB13};
 use crate::gpio::gpiob::{PB14, PB15, PB5};
 #[cfg(any(
     feature = "stm32f302",
@@ -65,8 +65,21 @@ use crate::gpio::gpiob::{PB3, PB4};
 use crate::gpio::gpioc::{PC10, PC11, PC12};
 #[cfg(any(feature = "stm32f373", feature = "stm32f378"))]
 use crate::gpio::gpioc::{PC2, PC3, PC7, PC8, PC9};
+#[cfg(any(
+    feature = "stm32f302xb",
+    feature = "stm32f302xc",
+    feature = "stm32f302xd",
+    feature = "stm32f302xe",
+    feature = "stm32f303xb",
+    feature = "stm32f303xc",
+    feature = "stm32f303xd",
+    feature = "stm32f303xe",
+    feature = "stm32f358",
+    feature = "stm32f398",
+))]
+use crate::gpio::gpiod::PD15;
 #[cfg(any(feature = "stm32f373", feature = "stm32f378"))]
-use crate::gpio::gpiod::{PD3, PD4, PD7, PD8};
+use crate::gpio::gpiod::{PD3, PD4, PD6, PD7, PD8};
 #[cfg(any(
     feature = "stm32f302xd",
     feature = "stm32f302xe",
@@ -74,7 +87,9 @@ use crate::gpio::gpiod::{PD3, PD4, PD7, PD8};
     feature = "stm32f303xe",
     feature = "stm32f398",
 ))]
-use crate::gpio::gpioe::{PE12, PE13, PE14, PE2, PE5, PE6};
+use crate::gpio::gpioe::{PE11, PE12, PE13, PE14, PE2, PE3, PE4, PE5, PE6};
+#[cfg(any(feature = "stm32f373", feature = "stm32f378"))]
+use crate::gpio::gpiof::PF6;
 #[cfg(any(
     feature = "stm32f302x6",
     feature = "stm32f302x8",
@@ -85,9 +100,7 @@ use crate::gpio::gpioe::{PE12, PE13, PE14, PE2, PE5, PE6};
     feature = "stm32f318",
     feature = "stm32f398",
 ))]
-use crate::gpio::gpiof::PF1;
-#[cfg(any(feature = "stm32f373", feature = "stm32f378"))]
-use crate::gpio::gpiof::PF6;
+use crate::gpio::gpiof::{PF0, PF1};
 #[cfg(any(
     feature = "stm32f302xb",
     feature = "stm32f302xc",
@@ -101,7 +114,8 @@ use crate::gpio::gpiof::PF6;
     feature = "stm32f398",
 ))]
 use crate::gpio::gpiof::{PF10, PF9};
-use crate::gpio::{PushPull, AF5, AF6};
+use crate::gpio::{marker, Edge, Pin, PushPull, AF5, AF6};
+use crate::pac::EXTI;
 use crate::rcc::Clocks;
 #[cfg(any(
     feature = "stm32f301",
@@ -131,9 +145,19 @@ use crate::rcc::APB1;
     feature = "stm32f398"
 ))]
 use crate::rcc::APB2;
+use crate::syscfg::SysCfg;
 use crate::time::rate::*;
 use core::marker::PhantomData;
 
+use cfg_if::cfg_if;
+
+cfg_if! {
+    if #[cfg(any(feature = "stm32f302", feature = "stm32f303"))] {
+        use crate::dma;
+        use cortex_m::interrupt;
+    }
+}
+
 /// SPI error
 #[derive(Debug)]
 #[non_exhaustive]
@@ -144,6 +168,8 @@ pub enum Error {
     ModeFault,
     /// CRC error
     Crc,
+    /// Frame format error, the NSS pulse of the TI frame format occurred at an unexpected time
+    FrameFormat,
 }
 
 // FIXME these should be "closed" traits
@@ -156,6 +182,36 @@ pub unsafe trait MisoPin<SPI> {}
 /// MOSI pin -- DO NOT IMPLEMENT THIS TRAIT
 pub unsafe trait MosiPin<SPI> {}
 
+/// NSS pin -- DO NOT IMPLEMENT THIS TRAIT
+pub unsafe trait NssPin<SPI> {}
+
+/// Placeholder for the MISO pin of a transmit-only SPI master
+///
+/// Passing `NoMiso` to the full duplex constructor leaves the MISO pin free for other uses.
+/// Received data is meaningless and should be discarded.
+pub struct NoMiso;
+
+unsafe impl MisoPin<SPI1> for NoMiso {}
+unsafe impl MisoPin<SPI2> for NoMiso {}
+unsafe impl MisoPin<SPI3> for NoMiso {}
+#[cfg(any(
+    feature = "stm32f302xd",
+    feature = "stm32f302xe",
+    feature = "stm32f303xd",
+    feature = "stm32f303xe",
+    feature = "stm32f398",
+))]
+unsafe impl MisoPin<SPI4> for NoMiso {}
+
+/// Behavior of the NSS output of an SPI master
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NssOutput {
+    /// NSS stays low while the SPI is enabled
+    Static,
+    /// NSS is pulsed high for one clock cycle between consecutive words
+    Pulse,
+}
+
 unsafe impl SckPin<SPI1> for PA5<AF5<PushPull>> {}
 #[cfg(any(feature = "stm32f373", feature = "stm32f378"))]
 unsafe impl SckPin<SPI1> for PA12<AF6<PushPull>> {}
@@ -385,8 +441,239 @@ unsafe impl MosiPin<SPI4> for PE6<AF5<PushPull>> {}
 ))]
 unsafe impl MosiPin<SPI4> for PE14<AF5<PushPull>> {}
 
+unsafe impl NssPin<SPI1> for PA4<AF5<PushPull>> {}
+unsafe impl NssPin<SPI1> for PA15<AF5<PushPull>> {}
+
+#[cfg(any(feature = "stm32f373", feature = "stm32f378"))]
+unsafe impl NssPin<SPI2> for PA11<AF5<PushPull>> {}
+#[cfg(any(feature = "stm32f373", feature = "stm32f378"))]
+unsafe impl NssPin<SPI2> for PB9<AF5<PushPull>> {}
+#[cfg(any(
+    feature = "stm32f301",
+    feature = "stm32f302",
+    feature = "stm32f303",
+    feature = "stm32f318",
+    feature = "stm32f334",
+    feature = "stm32f358",
+    feature = "stm32f398"
+))]
+unsafe impl NssPin<SPI2> for PB12<AF5<PushPull>> {}
+#[cfg(any(feature = "stm32f373", feature = "stm32f378"))]
+unsafe impl NssPin<SPI2> for PD6<AF5<PushPull>> {}
+#[cfg(any(
+    feature = "stm32f302xb",
+    feature = "stm32f302xc",
+    feature = "stm32f302xd",
+    feature = "stm32f302xe",
+    feature = "stm32f303xb",
+    feature = "stm32f303xc",
+    feature = "stm32f303xd",
+    feature = "stm32f303xe",
+    feature = "stm32f358",
+    feature = "stm32f398",
+))]
+unsafe impl NssPin<SPI2> for PD15<AF6<PushPull>> {}
+#[cfg(any(
+    feature = "stm32f302x6",
+    feature = "stm32f302x8",
+    feature = "stm32f302xd",
+    feature = "stm32f302xe",
+    feature = "stm32f303xd",
+    feature = "stm32f303xe",
+    feature = "stm32f318",
+    feature = "stm32f398",
+))]
+unsafe impl NssPin<SPI2> for PF0<AF5<PushPull>> {}
+
+unsafe impl NssPin<SPI3> for PA4<AF6<PushPull>> {}
+unsafe impl NssPin<SPI3> for PA15<AF6<PushPull>> {}
+
+#[cfg(any(
+    feature = "stm32f302xd",
+    feature = "stm32f302xe",
+    feature = "stm32f303xd",
+    feature = "stm32f303xe",
+    feature = "stm32f398",
+))]
+unsafe impl NssPin<SPI4> for PE3<AF5<PushPull>> {}
+#[cfg(any(
+    feature = "stm32f302xd",
+    feature = "stm32f302xe",
+    feature = "stm32f303xd",
+    feature = "stm32f303xe",
+    feature = "stm32f398",
+))]
+unsafe impl NssPin<SPI4> for PE4<AF5<PushPull>> {}
+#[cfg(any(
+    feature = "stm32f302xd",
+    feature = "stm32f302xe",
+    feature = "stm32f303xd",
+    feature = "stm32f303xe",
+    feature = "stm32f398",
+))]
+unsafe impl NssPin<SPI4> for PE11<AF5<PushPull>> {}
+
+/// Bit order of the transmitted and received data
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BitOrder {
+    /// Most significant bit first, the standard for SPI communication
+    MsbFirst,
+    /// Least significant bit first
+    LsbFirst,
+}
+
+/// Frame format of the SPI
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameFormat {
+    /// Motorola frame format, with the clock polarity and phase given by the [`Mode`]
+    Motorola,
+    /// TI synchronous serial frame format
+    ///
+    /// The clock polarity and phase are fixed by the frame format, the [`Mode`] is ignored.
+    /// A master generates the frame pulse on its NSS pin, so it has to be set up with
+    /// [`Spi::with_hardware_nss`].
+    Ti,
+}
+
+/// Length of the hardware calculated CRC
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CrcLength {
+    /// 8-bit CRC
+    Bits8,
+    /// 16-bit CRC
+    Bits16,
+}
+
+/// SPI configuration
+///
+/// Use `Config::default()` for the configuration set up by the constructors: Motorola frame
+/// format, MSB first and no CRC calculation.
+pub struct Config {
+    bit_order: BitOrder,
+    frame_format: FrameFormat,
+    crc_polynomial: Option<u16>,
+    crc_length: CrcLength,
+}
+
+impl Config {
+    /// Create a new `Config` using the settings of `Config::default()`.
+    pub fn new() -> Config {
+        Config::default()
+    }
+
+    /// Set the bit order.
+    pub fn bit_order(mut self, bit_order: BitOrder) -> Self {
+        self.bit_order = bit_order;
+        self
+    }
+
+    /// Set the frame format.
+    pub fn frame_format(mut self, frame_format: FrameFormat) -> Self {
+        self.frame_format = frame_format;
+        self
+    }
+
+    /// Enable the hardware CRC calculation with the given polynomial (CRCPOLY) and length
+    /// (CRCL).
+    ///
+    /// An 8-bit CRC requires a data size of 8 bits, a 16-bit CRC a data size of 16 bits. A
+    /// mismatching CRC is only reported by `verify_crc`, the received words are read as usual.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `polynomial` is even.
+    pub fn crc(mut self, polynomial: u16, length: CrcLength) -> Self {
+        crate::assert!(polynomial & 1 == 1, "the CRC polynomial has to be odd");
+        self.crc_polynomial = Some(polynomial);
+        self.crc_length = length;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bit_order: BitOrder::MsbFirst,
+            frame_format: FrameFormat::Motorola,
+            crc_polynomial: None,
+            crc_length: CrcLength::Bits8,
+        }
+    }
+}
+
+/// Applies the configuration, while the SPI is temporarily disabled
+///
+/// # Panics
+///
+/// Panics if the CRC length does not match the data size.
+fn configure(spi: &spi1::RegisterBlock, config: Config) {
+    if config.crc_polynomial.is_some() {
+        let data_size = match config.crc_length {
+            CrcLength::Bits8 => DS_A::EIGHTBIT,
+            CrcLength::Bits16 => DS_A::SIXTEENBIT,
+        };
+        crate::assert!(
+            spi.cr2.read().ds().bits() == u8::from(data_size),
+            "the CRC length has to match the data size"
+        );
+    }
+
+    // The SPI is enabled again afterwards only if it was enabled before, so a deselected
+    // hardware NSS stays released
+    let enabled = spi.cr1.read().spe().is_enabled();
+    spi.cr1.modify(|_, w| w.spe().disabled());
+
+    if let Some(polynomial) = config.crc_polynomial {
+        // NOTE(unsafe) the polynomial occupies the lower half of the register
+        spi.crcpr.write(|w| unsafe { w.bits(polynomial.into()) });
+    }
+    // FRF: frame format
+    spi.cr2
+        .modify(|_, w| w.frf().bit(config.frame_format == FrameFormat::Ti));
+    // LSBFIRST: bit order
+    // CRCL: CRC length
+    // CRCEN: hardware CRC calculation, disabling it also resets the CRC
+    spi.cr1.modify(|_, w| {
+        match config.bit_order {
+            BitOrder::MsbFirst => w.lsbfirst().msbfirst(),
+            BitOrder::LsbFirst => w.lsbfirst().lsbfirst(),
+        };
+        w.crcl().bit(config.crc_length == CrcLength::Bits16);
+        w.crcen().disabled()
+    });
+    if config.crc_polynomial.is_some() {
+        spi.cr1.modify(|_, w| w.crcen().enabled());
+    }
+
+    spi.cr1.modify(|_, w| w.spe().bit(enabled));
+}
+
+/// Resets the CRC calculation, while the SPI is temporarily disabled
+fn reset_crc(spi: &spi1::RegisterBlock) {
+    if spi.cr1.read().crcen().is_enabled() {
+        let enabled = spi.cr1.read().spe().is_enabled();
+        spi.cr1.modify(|_, w| w.spe().disabled());
+        spi.cr1.modify(|_, w| w.crcen().disabled());
+        spi.cr1.modify(|_, w| w.crcen().enabled());
+        spi.cr1.modify(|_, w| w.spe().bit(enabled));
+    }
+}
+
+/// Checks the CRC error flag and clears it
+fn verify_crc(spi: &spi1::RegisterBlock) -> Result<(), Error> {
+    if spi.sr.read().crcerr().is_no_match() {
+        spi.sr.modify(|_, w| w.crcerr().clear_bit());
+        Err(Error::Crc)
+    } else {
+        Ok(())
+    }
+}
+
 /// Configuration trait for the Word Size
 /// used by the SPI peripheral
+///
+/// Besides `u8` and `u16`, any data size from 4 to 16 bits is supported through [`Byte`]
+/// and [`HalfWord`].
 pub trait Word {
     /// Returns the register configuration
     /// to set the word size
@@ -405,6 +692,71 @@ impl Word for u16 {
     }
 }
 
+/// Word of 4 to 8 bits, e.g. `Byte<7>`
+///
+/// Each word occupies one byte in the FIFO and in memory, so words are moved by 8-bit
+/// accesses to the data register, also by DMA. The data is right aligned: the upper
+/// `8 - BITS` bits are ignored when sending and read as zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct Byte<const BITS: u8>(pub u8);
+
+// NOTE(unsafe) a transparent wrapper of `u8` is valid for every bit pattern
+unsafe impl<const BITS: u8> embedded_dma::Word for Byte<BITS> {}
+
+impl<const BITS: u8> Word for Byte<BITS> {
+    /// # Panics
+    ///
+    /// Panics if `BITS` is not in the range of 4 to 8.
+    fn register_config() -> (FRXTH_A, DS_A) {
+        crate::assert!((4..=8).contains(&BITS), "invalid data size of a byte");
+        // RXNE is set as soon as a single byte is received
+        (FRXTH_A::QUARTER, data_size(BITS))
+    }
+}
+
+/// Word of 9 to 16 bits, e.g. `HalfWord<12>`
+///
+/// Each word occupies two bytes in the FIFO and in memory, so words are moved by 16-bit
+/// accesses to the data register, also by DMA. The data is right aligned: the upper
+/// `16 - BITS` bits are ignored when sending and read as zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct HalfWord<const BITS: u8>(pub u16);
+
+// NOTE(unsafe) a transparent wrapper of `u16` is valid for every bit pattern
+unsafe impl<const BITS: u8> embedded_dma::Word for HalfWord<BITS> {}
+
+impl<const BITS: u8> Word for HalfWord<BITS> {
+    /// # Panics
+    ///
+    /// Panics if `BITS` is not in the range of 9 to 16.
+    fn register_config() -> (FRXTH_A, DS_A) {
+        crate::assert!((9..=16).contains(&BITS), "invalid data size of a half-word");
+        // RXNE is only set once both bytes of a word are received
+        (FRXTH_A::HALF, data_size(BITS))
+    }
+}
+
+fn data_size(bits: u8) -> DS_A {
+    match bits {
+        4 => DS_A::FOURBIT,
+        5 => DS_A::FIVEBIT,
+        6 => DS_A::SIXBIT,
+        7 => DS_A::SEVENBIT,
+        8 => DS_A::EIGHTBIT,
+        9 => DS_A::NINEBIT,
+        10 => DS_A::TENBIT,
+        11 => DS_A::ELEVENBIT,
+        12 => DS_A::TWELVEBIT,
+        13 => DS_A::THIRTEENBIT,
+        14 => DS_A::FOURTEENBIT,
+        15 => DS_A::FIFTEENBIT,
+        16 => DS_A::SIXTEENBIT,
+        _ => crate::unreachable!(),
+    }
+}
+
 /// SPI peripheral operating in full duplex master mode
 pub struct Spi<SPI, PINS, WORD = u8> {
     spi: SPI,
@@ -412,6 +764,143 @@ pub struct Spi<SPI, PINS, WORD = u8> {
     _word: PhantomData<WORD>,
 }
 
+/// SPI peripheral operating in full duplex slave mode
+///
+/// The slave is selected by the master through the hardware NSS input. The SPI has no
+/// underrun detection: if the master clocks out a word before a new one was written, the
+/// slave transmits the last word of its transmit FIFO again.
+pub struct SpiSlave<SPI, PINS, WORD = u8> {
+    spi: SPI,
+    pins: PINS,
+    _word: PhantomData<WORD>,
+}
+
+impl<SPI, SCK, MISO, MOSI, Gpio, Index, NssMode, WORD>
+    SpiSlave<SPI, (SCK, MISO, MOSI, Pin<Gpio, Index, NssMode>), WORD>
+where
+    Gpio: marker::Gpio,
+    Index: marker::Index,
+    NssMode: marker::Active,
+{
+    /// Generate an interrupt on the given edge of the NSS input
+    ///
+    /// A rising edge marks the end of a frame, a falling edge the start of the next one.
+    pub fn listen_nss(&mut self, edge: Edge, syscfg: &mut SysCfg, exti: &mut EXTI) {
+        let nss = &mut self.pins.3;
+        nss.make_interrupt_source(syscfg);
+        nss.trigger_on_edge(exti, edge);
+        nss.enable_interrupt(exti);
+    }
+
+    /// Stop generating interrupts on edges of the NSS input
+    pub fn unlisten_nss(&mut self, exti: &mut EXTI) {
+        self.pins.3.disable_interrupt(exti);
+    }
+
+    /// Return true if the configured edge of the NSS input occurred
+    pub fn is_nss_edge(&self) -> bool {
+        self.pins.3.check_interrupt()
+    }
+
+    /// Clear the NSS edge flag
+    pub fn clear_nss_edge(&mut self) {
+        self.pins.3.clear_interrupt_pending_bit();
+    }
+}
+
+/// SPI peripheral operating in bidirectional half-duplex master mode
+///
+/// A single data line on the MOSI pin is used for both directions, so the data is either
+/// written or read. Only writing to the device is a transmit-only mode, which needs no MISO
+/// pin.
+pub struct HalfDuplex<SPI, PINS, WORD = u8> {
+    spi: SPI,
+    pins: PINS,
+    /// Duration of a clock period in core clock cycles
+    sck_cycles: u32,
+    _word: PhantomData<WORD>,
+}
+
+/// SPI receiver
+///
+/// Only used as DMA target, see [`Spi::split`](Spi#method.split).
+pub struct Rx<SPI, WORD = u8> {
+    _spi: PhantomData<(SPI, WORD)>,
+}
+
+/// SPI transmitter
+///
+/// Only used as DMA target, see [`Spi::split`](Spi#method.split).
+pub struct Tx<SPI, WORD = u8> {
+    _spi: PhantomData<(SPI, WORD)>,
+}
+
+/// SPI driver, whose transmitter and receiver have been split off
+///
+/// Returned by [`Spi::split`](Spi#method.split) and
+/// [`SpiSlave::split`](SpiSlave#method.split). Owns the peripheral and the pins until the
+/// [`Tx`] and [`Rx`] halves are rejoined with `join`.
+pub struct Split<T> {
+    driver: T,
+}
+
+impl<SPI, WORD> Rx<SPI, WORD> {
+    pub(crate) fn new() -> Self {
+        Rx { _spi: PhantomData }
+    }
+}
+
+impl<SPI, WORD> Tx<SPI, WORD> {
+    pub(crate) fn new() -> Self {
+        Tx { _spi: PhantomData }
+    }
+}
+
+/// An in-progress full duplex DMA transfer
+///
+/// Started with [`Tx::transfer`](Tx#method.transfer).
+#[cfg(any(feature = "stm32f302", feature = "stm32f303"))]
+pub struct DuplexTransfer<TB, RB, TC, RC, SPI, WORD>
+where
+    TC: dma::Channel,
+    RC: dma::Channel,
+    Tx<SPI, WORD>: dma::Target,
+    Rx<SPI, WORD>: dma::Target,
+{
+    tx: dma::Transfer<TB, TC, Tx<SPI, WORD>>,
+    rx: dma::Transfer<RB, RC, Rx<SPI, WORD>>,
+}
+
+#[cfg(any(feature = "stm32f302", feature = "stm32f303"))]
+impl<TB, RB, TC, RC, SPI, WORD> DuplexTransfer<TB, RB, TC, RC, SPI, WORD>
+where
+    TC: dma::Channel,
+    RC: dma::Channel,
+    Tx<SPI, WORD>: dma::Target,
+    Rx<SPI, WORD>: dma::Target,
+{
+    /// Is this transfer complete?
+    ///
+    /// The transfer is complete, once the last word has been received.
+    pub fn is_complete(&self) -> bool {
+        self.tx.is_complete() && self.rx.is_complete()
+    }
+
+    /// Stop this transfer and return ownership over its parts
+    #[allow(clippy::type_complexity)]
+    pub fn stop(self) -> ((TB, TC, Tx<SPI, WORD>), (RB, RC, Rx<SPI, WORD>)) {
+        (self.tx.stop(), self.rx.stop())
+    }
+
+    /// Block until this transfer is done and return ownership over its parts
+    #[allow(clippy::type_complexity)]
+    pub fn wait(self) -> ((TB, TC, Tx<SPI, WORD>), (RB, RC, Rx<SPI, WORD>)) {
+        while !self.is_complete() {}
+
+        self.stop()
+    }
+}
+
 macro_rules! hal {
     ($($SPIX:ident: ($spiX:ident, $APBX:ident, $spiXen:ident, $spiXrst:ident, $pclkX:ident),)+) => {
         $(
@@ -486,21 +975,138 @@ macro_rules! hal {
                     Spi { spi, pins, _word: PhantomData }
                 }
 
+                /// Lets the SPI drive the NSS pin of a single slave device
+                ///
+                /// NSS is driven low as long as the SPI is enabled, so it stays asserted across
+                /// back-to-back (DMA) transfers. Transactions are framed by releasing NSS with
+                /// [`deselect`](Spi#method.deselect) and asserting it again with
+                /// [`select`](Spi#method.select). With [`NssOutput::Pulse`], NSS is
+                /// additionally pulsed high between consecutive words, which requires
+                /// [`Phase::CaptureOnFirstTransition`].
+                ///
+                /// The returned `Spi` is enabled, so NSS is asserted right away.
+                ///
+                /// # Panics
+                ///
+                /// Panics if [`NssOutput::Pulse`] is requested for a mode capturing on the second
+                /// clock transition.
+                pub fn with_hardware_nss<NSS>(
+                    self,
+                    nss: NSS,
+                    output: NssOutput,
+                ) -> Spi<$SPIX, (SCK, MISO, MOSI, NSS), WORD>
+                where
+                    NSS: NssPin<$SPIX>,
+                {
+                    let pulse = output == NssOutput::Pulse;
+                    crate::assert!(
+                        !pulse || self.spi.cr1.read().cpha().is_first_edge(),
+                        "NSS pulse mode requires the capture on the first clock transition"
+                    );
+
+                    self.spi.cr1.modify(|_, w| w.spe().disabled());
+
+                    // NSSP: NSS pulse between consecutive words
+                    // SSOE: NSS output enabled
+                    self.spi.cr2.modify(|_, w| {
+                        w.nssp().bit(pulse);
+                        w.ssoe().enabled()
+                    });
+                    // SSM: hardware slave management, NSS is driven by the SPI
+                    self.spi.cr1.modify(|_, w| {
+                        w.ssm().disabled();
+                        w.spe().enabled()
+                    });
+
+                    let (sck, miso, mosi) = self.pins;
+                    Spi {
+                        spi: self.spi,
+                        pins: (sck, miso, mosi, nss),
+                        _word: PhantomData,
+                    }
+                }
+            }
+
+            impl<SCK, MISO, MOSI, NSS, WORD> Spi<$SPIX, (SCK, MISO, MOSI, NSS), WORD>
+            where
+                NSS: NssPin<$SPIX>,
+            {
+                /// Asserts NSS by enabling the SPI, which starts a transaction
+                pub fn select(&mut self) {
+                    self.spi.cr1.modify(|_, w| w.spe().enabled());
+                }
+
+                /// Releases NSS by disabling the SPI, which ends a transaction
+                ///
+                /// Waits until the last word has been transmitted. Received words, which have
+                /// not been read yet, are kept in the receive FIFO.
+                pub fn deselect(&mut self) {
+                    while !self.spi.sr.read().ftlvl().is_empty() {}
+                    while self.spi.sr.read().bsy().is_busy() {}
+                    self.spi.cr1.modify(|_, w| w.spe().disabled());
+                }
+            }
+
+            impl<PINS, WORD> Spi<$SPIX, PINS, WORD> {
                 /// Releases the SPI peripheral and associated pins
-                pub fn free(self) -> ($SPIX, (SCK, MISO, MOSI)) {
+                pub fn free(self) -> ($SPIX, PINS) {
                     (self.spi, self.pins)
                 }
 
+                /// Splits the `Spi` abstraction into a transmitter and a receiver half
+                ///
+                /// The halves are used for DMA transfers. The peripheral and the pins are kept
+                /// in the returned [`Split`] until the halves are rejoined.
+                pub fn split(self) -> (Tx<$SPIX, WORD>, Rx<$SPIX, WORD>, Split<Self>) {
+                    (Tx::new(), Rx::new(), Split { driver: self })
+                }
+
                 /// Change the baud rate of the SPI
                 pub fn reclock(&mut self, freq: Hertz, clocks: Clocks) {
+                    let enabled = self.spi.cr1.read().spe().is_enabled();
                     self.spi.cr1.modify(|_, w| w.spe().disabled());
 
                     self.spi.cr1.modify(|_, w| {
                         w.br().variant(Self::compute_baud_rate(clocks.$pclkX(), freq));
-                        w.spe().enabled()
+                        w.spe().bit(enabled)
                     });
                 }
 
+                /// Applies the bit order, frame format and CRC configuration
+                ///
+                /// This must not be called while a transfer is ongoing.
+                ///
+                /// # Panics
+                ///
+                /// Panics if the CRC length does not match the data size.
+                pub fn configure(&mut self, config: Config) {
+                    configure(&self.spi, config);
+                }
+
+                /// Transmits the calculated CRC after the current word
+                ///
+                /// Call this right after the last data word of a CRC protected block has been
+                /// sent. The CRC is received like a data word, so it has to be read, once the
+                /// CRC of the other side was received, before checking it with
+                /// [`verify_crc`](Self::verify_crc).
+                pub fn send_crc(&mut self) {
+                    self.spi.cr2.modify(|_, w| w.crcnext().set_bit());
+                }
+
+                /// Returns [`Error::Crc`], if the received CRC did not match the calculated one
+                ///
+                /// The CRC error flag is cleared.
+                pub fn verify_crc(&mut self) -> Result<(), Error> {
+                    verify_crc(&self.spi)
+                }
+
+                /// Resets the CRC calculation for the next block
+                ///
+                /// This must not be called while a transfer is ongoing.
+                pub fn reset_crc(&mut self) {
+                    reset_crc(&self.spi);
+                }
+
                 fn compute_baud_rate(clocks: Hertz, freq: Hertz) -> spi1::cr1::BR_A {
                     use spi1::cr1::BR_A;
                     match clocks.0 / freq.integer() {
@@ -519,6 +1125,359 @@ macro_rules! hal {
 
             }
 
+            impl<PINS, WORD> Split<Spi<$SPIX, PINS, WORD>> {
+                /// Rejoins the transmitter and the receiver with the `Spi`
+                pub fn join(
+                    self,
+                    _tx: Tx<$SPIX, WORD>,
+                    _rx: Rx<$SPIX, WORD>,
+                ) -> Spi<$SPIX, PINS, WORD> {
+                    self.driver
+                }
+            }
+
+            impl<PINS, WORD> Split<SpiSlave<$SPIX, PINS, WORD>> {
+                /// Rejoins the transmitter and the receiver with the `SpiSlave`
+                pub fn join(
+                    self,
+                    _tx: Tx<$SPIX, WORD>,
+                    _rx: Rx<$SPIX, WORD>,
+                ) -> SpiSlave<$SPIX, PINS, WORD> {
+                    self.driver
+                }
+            }
+
+            impl<SCK, MISO, MOSI, NSS, WORD> SpiSlave<$SPIX, (SCK, MISO, MOSI, NSS), WORD> {
+                /// Configures the SPI peripheral to operate in full duplex slave mode
+                pub fn $spiX(
+                    spi: $SPIX,
+                    pins: (SCK, MISO, MOSI, NSS),
+                    mode: Mode,
+                    apb: &mut $APBX,
+                ) -> Self
+                where
+                    SCK: SckPin<$SPIX>,
+                    MISO: MisoPin<$SPIX>,
+                    MOSI: MosiPin<$SPIX>,
+                    NSS: NssPin<$SPIX>,
+                    WORD: Word,
+                {
+                    // enable or reset $SPIX
+                    apb.enr().modify(|_, w| w.$spiXen().enabled());
+                    apb.rstr().modify(|_, w| w.$spiXrst().reset());
+                    apb.rstr().modify(|_, w| w.$spiXrst().clear_bit());
+
+                    let (frxth, ds) = WORD::register_config();
+                    spi.cr2.write(|w| {
+                        w.frxth().variant(frxth);
+                        w.ds().variant(ds);
+                        w.ssoe().disabled()
+                    });
+
+                    // MSTR: slave mode
+                    // SSM: hardware slave management, the NSS pin selects the slave
+                    spi.cr1.write(|w| {
+                        w.mstr().slave();
+
+                        match mode.phase {
+                            Phase::CaptureOnFirstTransition => w.cpha().first_edge(),
+                            Phase::CaptureOnSecondTransition => w.cpha().second_edge(),
+                        };
+
+                        match mode.polarity {
+                            Polarity::IdleLow => w.cpol().idle_low(),
+                            Polarity::IdleHigh => w.cpol().idle_high(),
+                        };
+
+                        w.spe()
+                            .enabled()
+                            .lsbfirst()
+                            .msbfirst()
+                            .ssm()
+                            .disabled()
+                            .crcen()
+                            .disabled()
+                            .bidimode()
+                            .unidirectional()
+                    });
+
+                    SpiSlave { spi, pins, _word: PhantomData }
+                }
+
+                /// Releases the SPI peripheral and associated pins
+                pub fn free(self) -> ($SPIX, (SCK, MISO, MOSI, NSS)) {
+                    self.spi.cr1.modify(|_, w| w.spe().disabled());
+                    (self.spi, self.pins)
+                }
+
+                /// Splits the `SpiSlave` abstraction into a transmitter and a receiver half
+                ///
+                /// The halves are used for DMA transfers. The peripheral and the pins are kept
+                /// in the returned [`Split`] until the halves are rejoined.
+                pub fn split(self) -> (Tx<$SPIX, WORD>, Rx<$SPIX, WORD>, Split<Self>) {
+                    (Tx::new(), Rx::new(), Split { driver: self })
+                }
+            }
+
+            impl<PINS, WORD> SpiSlave<$SPIX, PINS, WORD> {
+                /// Applies the bit order, frame format and CRC configuration
+                ///
+                /// This must not be called while a transfer is ongoing.
+                ///
+                /// # Panics
+                ///
+                /// Panics if the CRC length does not match the data size.
+                pub fn configure(&mut self, config: Config) {
+                    configure(&self.spi, config);
+                }
+
+                /// Transmits the calculated CRC after the current word
+                ///
+                /// Call this right after the last data word of a CRC protected block has been
+                /// sent. The CRC is received like a data word, so it has to be read, once the
+                /// CRC of the other side was received, before checking it with
+                /// [`verify_crc`](Self::verify_crc).
+                pub fn send_crc(&mut self) {
+                    self.spi.cr2.modify(|_, w| w.crcnext().set_bit());
+                }
+
+                /// Returns [`Error::Crc`], if the received CRC did not match the calculated one
+                ///
+                /// The CRC error flag is cleared.
+                pub fn verify_crc(&mut self) -> Result<(), Error> {
+                    verify_crc(&self.spi)
+                }
+
+                /// Resets the CRC calculation for the next block
+                ///
+                /// This must not be called while a transfer is ongoing.
+                pub fn reset_crc(&mut self) {
+                    reset_crc(&self.spi);
+                }
+            }
+
+            impl<PINS, WORD> FullDuplex<WORD> for SpiSlave<$SPIX, PINS, WORD> {
+                type Error = Error;
+
+                /// Read the next received word
+                ///
+                /// Returns [`Error::Overrun`] once, if words were lost because the receive
+                /// FIFO was full.
+                fn read(&mut self) -> nb::Result<WORD, Error> {
+                    let sr = self.spi.sr.read();
+
+                    Err(if sr.ovr().is_overrun() {
+                        // The overrun flag is cleared by reading DR and then SR
+                        let _ = self.spi.dr.read();
+                        let _ = self.spi.sr.read();
+                        nb::Error::Other(Error::Overrun)
+                    } else if sr.fre().bit_is_set() {
+                        // The frame format error flag is cleared by reading SR
+                        nb::Error::Other(Error::FrameFormat)
+                    } else if sr.rxne().is_not_empty() {
+                        let read_ptr = &self.spi.dr as *const _ as *const WORD;
+                        // NOTE(unsafe) read from register owned by this SpiSlave struct
+                        let value = unsafe { ptr::read_volatile(read_ptr) };
+                        return Ok(value);
+                    } else {
+                        nb::Error::WouldBlock
+                    })
+                }
+
+                /// Queue a word for transmission during the next transfer of the master
+                fn send(&mut self, word: WORD) -> nb::Result<(), Error> {
+                    if self.spi.sr.read().txe().is_empty() {
+                        let write_ptr = &self.spi.dr as *const _ as *mut WORD;
+                        // NOTE(unsafe) write to register owned by this SpiSlave struct
+                        unsafe { ptr::write_volatile(write_ptr, word) };
+                        Ok(())
+                    } else {
+                        Err(nb::Error::WouldBlock)
+                    }
+                }
+            }
+
+            impl<SCK, DATA, WORD> HalfDuplex<$SPIX, (SCK, DATA), WORD> {
+                /// Configures the SPI peripheral to operate in bidirectional half-duplex
+                /// master mode, with the MOSI pin as data line
+                pub fn $spiX(
+                    spi: $SPIX,
+                    pins: (SCK, DATA),
+                    mode: Mode,
+                    freq: Hertz,
+                    clocks: Clocks,
+                    apb: &mut $APBX,
+                ) -> Self
+                where
+                    SCK: SckPin<$SPIX>,
+                    DATA: MosiPin<$SPIX>,
+                    WORD: Word,
+                {
+                    // enable or reset $SPIX
+                    apb.enr().modify(|_, w| w.$spiXen().enabled());
+                    apb.rstr().modify(|_, w| w.$spiXrst().reset());
+                    apb.rstr().modify(|_, w| w.$spiXrst().clear_bit());
+
+                    let (frxth, ds) = WORD::register_config();
+                    spi.cr2.write(|w| {
+                        w.frxth().variant(frxth);
+                        w.ds().variant(ds);
+                        w.ssoe().disabled()
+                    });
+
+                    // BIDIMODE: 1 line bidirectional (half duplex)
+                    // BIDIOE: output enabled, no clock is generated until data is written
+                    spi.cr1.write(|w| {
+                        w.mstr().master();
+
+                        match mode.phase {
+                            Phase::CaptureOnFirstTransition => w.cpha().first_edge(),
+                            Phase::CaptureOnSecondTransition => w.cpha().second_edge(),
+                        };
+
+                        match mode.polarity {
+                            Polarity::IdleLow => w.cpol().idle_low(),
+                            Polarity::IdleHigh => w.cpol().idle_high(),
+                        };
+
+                        w.br().variant(Spi::<$SPIX, (SCK, DATA), WORD>::compute_baud_rate(
+                            clocks.$pclkX(),
+                            freq,
+                        ));
+
+                        w.spe()
+                            .enabled()
+                            .lsbfirst()
+                            .msbfirst()
+                            .ssi()
+                            .slave_not_selected()
+                            .ssm()
+                            .enabled()
+                            .crcen()
+                            .disabled()
+                            .bidimode()
+                            .bidirectional()
+                            .bidioe()
+                            .output_enabled()
+                    });
+
+                    // SCK runs at PCLK divided by 2 to the power of BR + 1
+                    let divider = 2 << spi.cr1.read().br().bits();
+                    let sck_cycles = clocks.sysclk().0 / clocks.$pclkX().0 * divider;
+
+                    HalfDuplex {
+                        spi,
+                        pins,
+                        sck_cycles,
+                        _word: PhantomData,
+                    }
+                }
+
+                /// Releases the SPI peripheral and associated pins
+                pub fn free(self) -> ($SPIX, (SCK, DATA)) {
+                    self.spi.cr1.modify(|_, w| w.spe().disabled());
+                    (self.spi, self.pins)
+                }
+            }
+
+            impl<PINS, WORD> HalfDuplex<$SPIX, PINS, WORD>
+            where
+                WORD: Copy,
+            {
+                /// Writes the words to the data line
+                ///
+                /// Returns once the last word has been shifted out.
+                pub fn write(&mut self, words: &[WORD]) -> Result<(), Error> {
+                    self.spi.cr1.modify(|_, w| {
+                        w.bidioe().output_enabled();
+                        w.spe().enabled()
+                    });
+
+                    for &word in words {
+                        loop {
+                            let sr = self.spi.sr.read();
+                            if sr.modf().is_fault() {
+                                return Err(Error::ModeFault);
+                            } else if sr.txe().is_empty() {
+                                break;
+                            }
+                        }
+
+                        let write_ptr = &self.spi.dr as *const _ as *mut WORD;
+                        // NOTE(unsafe) write to register owned by this HalfDuplex struct
+                        unsafe { ptr::write_volatile(write_ptr, word) };
+                    }
+
+                    while !self.spi.sr.read().ftlvl().is_empty() {}
+                    while self.spi.sr.read().bsy().is_busy() {}
+                    Ok(())
+                }
+
+                /// Reads words from the data line until `words` is filled
+                ///
+                /// As the clock runs continuously while reading, the SPI is disabled during the
+                /// reception of the last word, which stops the clock after that word. This
+                /// function should not be interrupted for longer than a word time, otherwise
+                /// excess words are clocked out of the device. It returns once the clock has
+                /// stopped.
+                pub fn read(&mut self, words: &mut [WORD]) -> Result<(), Error> {
+                    self.spi.cr1.modify(|_, w| w.spe().disabled());
+                    // Drain words left over from an aborted read
+                    while !self.spi.sr.read().frlvl().is_empty() {
+                        let _ = self.spi.dr.read();
+                    }
+                    // The clock starts as soon as the SPI is enabled with the output disabled
+                    self.spi.cr1.modify(|_, w| w.bidioe().output_disabled());
+                    self.spi.cr1.modify(|_, w| w.spe().enabled());
+
+                    let len = words.len();
+                    for (i, word) in words.iter_mut().enumerate() {
+                        if i + 1 == len {
+                            // SPE may only be cleared after the first bit of the last word
+                            // has been sampled, which takes at most one clock period after
+                            // the previous word was received
+                            cortex_m::asm::delay(self.sck_cycles);
+                            // The last word is being received
+                            self.spi.cr1.modify(|_, w| w.spe().disabled());
+                        }
+
+                        loop {
+                            let sr = self.spi.sr.read();
+                            if sr.ovr().is_overrun() {
+                                self.spi.cr1.modify(|_, w| w.spe().disabled());
+                                return Err(Error::Overrun);
+                            } else if sr.modf().is_fault() {
+                                self.spi.cr1.modify(|_, w| w.spe().disabled());
+                                return Err(Error::ModeFault);
+                            } else if sr.rxne().is_not_empty() {
+                                break;
+                            }
+                        }
+
+                        let read_ptr = &self.spi.dr as *const _ as *const WORD;
+                        // NOTE(unsafe) read from register owned by this HalfDuplex struct
+                        *word = unsafe { ptr::read_volatile(read_ptr) };
+                    }
+
+                    if len == 0 {
+                        self.spi.cr1.modify(|_, w| w.spe().disabled());
+                    }
+                    while self.spi.sr.read().bsy().is_busy() {}
+                    Ok(())
+                }
+            }
+
+            impl<PINS, WORD> crate::hal::blocking::spi::Write<WORD> for HalfDuplex<$SPIX, PINS, WORD>
+            where
+                WORD: Copy,
+            {
+                type Error = Error;
+
+                fn write(&mut self, words: &[WORD]) -> Result<(), Error> {
+                    HalfDuplex::write(self, words)
+                }
+            }
+
             impl<PINS, WORD> FullDuplex<WORD> for Spi<$SPIX, PINS, WORD> {
                 type Error = Error;
 
@@ -529,8 +1488,6 @@ macro_rules! hal {
                         nb::Error::Other(Error::Overrun)
                     } else if sr.modf().is_fault() {
                         nb::Error::Other(Error::ModeFault)
-                    } else if sr.crcerr().is_no_match() {
-                        nb::Error::Other(Error::Crc)
                     } else if sr.rxne().is_not_empty() {
                         let read_ptr = &self.spi.dr as *const _ as *const WORD;
                         // NOTE(unsafe) read from register owned by this Spi struct
@@ -548,8 +1505,6 @@ macro_rules! hal {
                         nb::Error::Other(Error::Overrun)
                     } else if sr.modf().is_fault() {
                         nb::Error::Other(Error::ModeFault)
-                    } else if sr.crcerr().is_no_match() {
-                        nb::Error::Other(Error::Crc)
                     } else if sr.txe().is_empty() {
                         let write_ptr = &self.spi.dr as *const _ as *mut WORD;
                         // NOTE(unsafe) write to register owned by this Spi struct
@@ -563,6 +1518,186 @@ macro_rules! hal {
 
             impl<PINS, WORD> crate::hal::blocking::spi::transfer::Default<WORD> for Spi<$SPIX, PINS, WORD> {}
             impl<PINS, WORD> crate::hal::blocking::spi::write::Default<WORD> for Spi<$SPIX, PINS, WORD> {}
+
+            #[cfg(any(feature = "stm32f302", feature = "stm32f303"))]
+            impl<WORD> Rx<$SPIX, WORD> {
+                /// Fill the buffer with received words using DMA.
+                ///
+                /// In master mode, words are only received while words are transmitted. Use
+                /// [`Tx::transfer`] to receive and transmit at the same time.
+                pub fn read_exact<B, C>(
+                    self,
+                    buffer: B,
+                    mut channel: C
+                ) -> dma::Transfer<B, C, Self>
+                where
+                    Self: dma::OnChannel<C>,
+                    B: dma::WriteBuffer<Word = WORD> + 'static,
+                    C: dma::Channel,
+                {
+                    // NOTE(unsafe) taking the address of a register
+                    let pa = unsafe { &(*$SPIX::ptr()).dr } as *const _ as u32;
+                    // NOTE(unsafe) usage of a valid peripheral address
+                    unsafe { channel.set_peripheral_address(pa, dma::Increment::Disable) };
+
+                    dma::Transfer::start_write(buffer, channel, self)
+                }
+
+                /// Continuously receive into the buffer using DMA, wrapping around at its end.
+                ///
+                /// This is meant for streams like I2S audio, which never stop receiving.
+                pub fn read_circular<B, C>(
+                    self,
+                    buffer: B,
+                    mut channel: C
+                ) -> dma::CircTransfer<B, C, Self>
+                where
+                    Self: dma::OnChannel<C>,
+                    B: dma::WriteBuffer<Word = WORD> + 'static,
+                    C: dma::Channel,
+                {
+                    // NOTE(unsafe) taking the address of a register
+                    let pa = unsafe { &(*$SPIX::ptr()).dr } as *const _ as u32;
+                    // NOTE(unsafe) usage of a valid peripheral address
+                    unsafe { channel.set_peripheral_address(pa, dma::Increment::Disable) };
+
+                    dma::CircTransfer::start_write(buffer, channel, self)
+                }
+
+                /// Drop all received words and clear the overrun flag
+                ///
+                /// This is required after a transmit-only transfer with
+                /// [`Tx::write_all`], which leaves the receiver overrun.
+                pub fn clear_overrun(&mut self) {
+                    // NOTE(unsafe) reading the data register only pops the receive FIFO,
+                    // which is owned by this receiver
+                    let spi = unsafe { &*$SPIX::ptr() };
+                    while spi.sr.read().rxne().is_not_empty() {
+                        let _ = spi.dr.read();
+                    }
+                    // The overrun flag is cleared by reading DR and then SR
+                    let _ = spi.sr.read();
+                }
+            }
+
+            #[cfg(any(feature = "stm32f302", feature = "stm32f303"))]
+            impl<WORD> Tx<$SPIX, WORD> {
+                /// Transmit all words of the buffer using DMA.
+                ///
+                /// The received words are discarded, which overruns the receiver. See
+                /// [`Rx::clear_overrun`].
+                pub fn write_all<B, C>(
+                    self,
+                    buffer: B,
+                    mut channel: C
+                ) -> dma::Transfer<B, C, Self>
+                where
+                    Self: dma::OnChannel<C>,
+                    B: dma::ReadBuffer<Word = WORD> + 'static,
+                    C: dma::Channel,
+                {
+                    // NOTE(unsafe) taking the address of a register
+                    let pa = unsafe { &(*$SPIX::ptr()).dr } as *const _ as u32;
+                    // NOTE(unsafe) usage of a valid peripheral address
+                    unsafe { channel.set_peripheral_address(pa, dma::Increment::Disable) };
+
+                    dma::Transfer::start_read(buffer, channel, self)
+                }
+
+                /// Continuously transmit the buffer using DMA, restarting at its beginning.
+                ///
+                /// This is meant for streams like I2S audio, which never stop transmitting.
+                pub fn write_circular<B, C>(
+                    self,
+                    buffer: B,
+                    mut channel: C
+                ) -> dma::CircTransfer<B, C, Self>
+                where
+                    Self: dma::OnChannel<C>,
+                    B: dma::ReadBuffer<Word = WORD> + 'static,
+                    C: dma::Channel,
+                {
+                    // NOTE(unsafe) taking the address of a register
+                    let pa = unsafe { &(*$SPIX::ptr()).dr } as *const _ as u32;
+                    // NOTE(unsafe) usage of a valid peripheral address
+                    unsafe { channel.set_peripheral_address(pa, dma::Increment::Disable) };
+
+                    dma::CircTransfer::start_read(buffer, channel, self)
+                }
+
+                /// Transmit all words of `tx_buffer` and receive into `rx_buffer` at the
+                /// same time using two DMA channels.
+                ///
+                /// # Panics
+                ///
+                /// Panics if the buffers have different lengths. The receiver would wait
+                /// forever for missing words, or overrun on surplus words.
+                pub fn transfer<TB, RB, TC, RC>(
+                    self,
+                    rx: Rx<$SPIX, WORD>,
+                    tx_buffer: TB,
+                    mut rx_buffer: RB,
+                    tx_channel: TC,
+                    rx_channel: RC,
+                ) -> DuplexTransfer<TB, RB, TC, RC, $SPIX, WORD>
+                where
+                    Self: dma::OnChannel<TC>,
+                    Rx<$SPIX, WORD>: dma::OnChannel<RC>,
+                    TB: dma::ReadBuffer<Word = WORD> + 'static,
+                    RB: dma::WriteBuffer<Word = WORD> + 'static,
+                    TC: dma::Channel,
+                    RC: dma::Channel,
+                {
+                    // NOTE(unsafe) only the lengths of the buffers are used
+                    let (_, tx_len) = unsafe { tx_buffer.read_buffer() };
+                    let (_, rx_len) = unsafe { rx_buffer.write_buffer() };
+                    crate::assert!(tx_len == rx_len, "buffers have different lengths");
+
+                    // The receiver has to be ready before the first word is transmitted
+                    let rx = rx.read_exact(rx_buffer, rx_channel);
+                    let tx = self.write_all(tx_buffer, tx_channel);
+
+                    DuplexTransfer { tx, rx }
+                }
+            }
+
+            #[cfg(any(feature = "stm32f302", feature = "stm32f303"))]
+            impl<WORD> dma::Target for Rx<$SPIX, WORD> {
+                fn enable_dma(&mut self) {
+                    // NOTE(unsafe) critical section prevents races
+                    interrupt::free(|_| unsafe {
+                        let cr2 = &(*$SPIX::ptr()).cr2;
+                        cr2.modify(|_, w| w.rxdmaen().enabled());
+                    });
+                }
+
+                fn disable_dma(&mut self) {
+                    // NOTE(unsafe) critical section prevents races
+                    interrupt::free(|_| unsafe {
+                        let cr2 = &(*$SPIX::ptr()).cr2;
+                        cr2.modify(|_, w| w.rxdmaen().disabled());
+                    });
+                }
+            }
+
+            #[cfg(any(feature = "stm32f302", feature = "stm32f303"))]
+            impl<WORD> dma::Target for Tx<$SPIX, WORD> {
+                fn enable_dma(&mut self) {
+                    // NOTE(unsafe) critical section prevents races
+                    interrupt::free(|_| unsafe {
+                        let cr2 = &(*$SPIX::ptr()).cr2;
+                        cr2.modify(|_, w| w.txdmaen().enabled());
+                    });
+                }
+
+                fn disable_dma(&mut self) {
+                    // NOTE(unsafe) critical section prevents races
+                    interrupt::free(|_| unsafe {
+                        let cr2 = &(*$SPIX::ptr()).cr2;
+                        cr2.modify(|_, w| w.txdmaen().disabled());
+                    });
+                }
+            }
         )+
     }
 }